@@ -0,0 +1,123 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use novel_archives_text::parser::token::{
+    dialect, iterator::TextIterator, ParseContext, ParsedSpan, ParserOptions,
+};
+use novel_archives_text::term::Term;
+use novel_archives_text::{Id, TokenText};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const CORPUS_LEN: usize = 2 * 1024 * 1024;
+const GLOSSARY_LEN: usize = 5000;
+
+const NOVEL_ARCHIVES_CHAPTER: &str = "\
+# 第一話　|夜明(よあ)けの街
+
+　朝靄の立ち込める王都を、\"穂積\"はひとりで歩いていた。石畳(いしだたみ)に響く足音だけが、まだ眠る街の静けさを破っている。
+「こんな時間に出歩くなんて、らしくないわね」
+　背後からかけられた声に振り返ると、幼馴染(おさななじみ)の少女が呆れたように笑っていた。
+「眠れなかったんだ。《《あの夢》》を、また見た」
+「……また？　『扉』の夢？」
+　\"穂積\"は小さく頷いた。夢の中で彼はいつも、古(ふる)びた|扉(とびら)の前に立っている。扉の向こうからは、誰かが彼の名を呼ぶ声が聞こえるのだ。
+　|王立図書館$この国で最も古い図書館。禁書庫には|魔導書(グリモワール)が眠るという。$の鐘が、六つ鳴った。
+
+＊＊＊
+
+　市場には既に商人たちが集まり始めていた。焼きたてのパンの匂いが、空腹を思い出させる。
+「ねえ、朝ごはん食べていかない？　おごってあげる」
+「本当に？」
+「その代わり、夢の話をちゃんと聞かせてよね」
+　彼女はそう言って、《《白ゴマ|いたずらっぽく》》片目を瞑(つむ)った。
+";
+
+const AOZORA_CHAPTER: &str = "　朝靄の立ち込める王都を、穂積《ほづみ》はひとりで歩いていた。石畳《いしだたみ》に響く足音だけが、まだ眠る街の静けさを破っている。
+「こんな時間に出歩くなんて、らしくないわね」
+　背後からかけられた声に振り返ると、｜幼馴染《おさななじみ》の少女が呆れたように笑っていた。
+「眠れなかったんだ。あの夢を、また見た」［＃「あの夢」に傍点］
+　穂積は小さく頷いた。夢の中で彼はいつも、古《ふる》びた扉の前に立っている。
+［＃改ページ］
+";
+
+const PROSE_CHAPTER: &str = "　朝靄の立ち込める王都を、彼はひとりで歩いていた。石畳に響く足音だけが、まだ眠る街の静けさを破っている。市場には既に商人たちが集まり始めていた。焼きたてのパンの匂いが、空腹を思い出させる。
+";
+
+fn corpus(chapter: &str) -> String {
+    chapter.repeat(CORPUS_LEN / chapter.len() + 1)
+}
+
+fn term_context(terms: impl IntoIterator<Item = Term>) -> ParseContext {
+    ParseContext::new(Arc::new(
+        terms
+            .into_iter()
+            .map(|term| (term.body().clone(), term))
+            .collect::<BTreeMap<_, _>>(),
+    ))
+}
+
+fn context() -> ParseContext {
+    term_context(vec![Term::new(
+        Id::new("hozumi"),
+        "穂積".into(),
+        "ほづみ".into(),
+        "".into(),
+        false,
+    )])
+}
+
+fn glossary_context() -> ParseContext {
+    let terms = (0..GLOSSARY_LEN)
+        .map(|i| {
+            Term::new(
+                Id::new(format!("term{}", i)),
+                format!("魔導具{}号", i),
+                "".into(),
+                "".into(),
+                false,
+            )
+        })
+        .chain(std::iter::once(Term::new(
+            Id::new("library"),
+            "王立図書館".into(),
+            "おうりつとしょかん".into(),
+            "".into(),
+            false,
+        )));
+    term_context(terms).with_options(ParserOptions::default().with_unquoted_term(true))
+}
+
+fn parse(context: &ParseContext, input: &str) -> usize {
+    let text: TokenText = TextIterator::new(context.clone(), ParsedSpan::new(input)).collect();
+    text.len()
+}
+
+fn parse_benchmark(c: &mut Criterion) {
+    let cases = [
+        ("novel-archives", corpus(NOVEL_ARCHIVES_CHAPTER), context()),
+        (
+            "narou",
+            corpus(NOVEL_ARCHIVES_CHAPTER),
+            context().with_dialect(dialect::from_name("narou").unwrap()),
+        ),
+        (
+            "aozora",
+            corpus(AOZORA_CHAPTER),
+            context().with_dialect(dialect::from_name("aozora").unwrap()),
+        ),
+        ("prose", corpus(PROSE_CHAPTER), context()),
+        (
+            "unquoted-glossary",
+            corpus(NOVEL_ARCHIVES_CHAPTER),
+            glossary_context(),
+        ),
+    ];
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    for (name, input, context) in cases.iter() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| parse(context, black_box(input))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse_benchmark);
+criterion_main!(benches);
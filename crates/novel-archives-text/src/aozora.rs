@@ -0,0 +1,224 @@
+use crate::parser::nom_extend::character;
+use crate::{Token, TokenText};
+use std::fmt::Write;
+
+const DIRECTIVE: &str = "｜";
+const NEW_LINE: &str = "\r\n";
+const GAIJI_MARK: &str = "※";
+const ESCAPES: [(char, &str); 6] = [
+    ('《', "※［＃始め二重山括弧、1-1-52］"),
+    ('》', "※［＃終わり二重山括弧、1-1-53］"),
+    ('［', "※［＃始め角括弧、1-1-46］"),
+    ('］', "※［＃終わり角括弧、1-1-47］"),
+    ('｜', "※［＃縦線、1-1-35］"),
+    ('※', "※［＃米印、1-2-8］"),
+];
+
+pub fn export(text: &TokenText) -> String {
+    let mut s = String::new();
+    write_text(&mut s, text);
+    s
+}
+
+fn write_text(s: &mut String, text: &TokenText) {
+    for token in text.iter() {
+        write_token(s, token);
+    }
+}
+
+fn write_token(s: &mut String, token: &Token) {
+    match token {
+        Token::Term { body, .. } => write_escaped(s, body.body()),
+        Token::Ruby { body, ruby, .. } | Token::KanjiRuby { body, ruby, .. } => {
+            write_ruby(s, body.body(), ruby.body())
+        }
+        Token::MonoRuby { pairs, .. } => {
+            for pair in pairs {
+                write_ruby(s, pair.body().body(), pair.ruby().body());
+            }
+        }
+        Token::Annotation {
+            body, description, ..
+        } => {
+            let flat_body = flatten(body, false);
+            let target = flatten(body, true);
+            let description = flatten(description, true);
+            if flat_body.is_empty() {
+                write!(s, "［＃{}］", description).unwrap();
+            } else if flat_body == GAIJI_MARK {
+                write!(s, "{}［＃{}］", flat_body, description).unwrap();
+            } else if description.starts_with(&format!("「{}」", target)) {
+                write_text(s, body);
+                write!(s, "［＃{}］", description).unwrap();
+            } else {
+                write_text(s, body);
+                write!(s, "［＃「{}」に「{}」の注記］", target, description).unwrap();
+            }
+        }
+        Token::EmphasisMark { body, style, .. } => {
+            let target = flatten(body, true);
+            if target == flatten(body, false) {
+                write_text(s, body);
+                write!(s, "［＃「{}」に{}］", target, style.aozora_name()).unwrap();
+            } else {
+                write!(s, "［＃{}］", style.aozora_name()).unwrap();
+                write_text(s, body);
+                write!(s, "［＃{}終わり］", style.aozora_name()).unwrap();
+            }
+        }
+        Token::Escape { body, .. } | Token::Spase(body) | Token::Plaintext(body) => {
+            write_escaped(s, body.body())
+        }
+        Token::Ignore(_) => {}
+        Token::NewLine(_) => s.push_str(NEW_LINE),
+    }
+}
+
+fn write_ruby(s: &mut String, body: &str, ruby: &str) {
+    if needs_directive(s, body) {
+        s.push_str(DIRECTIVE);
+    }
+    write_escaped(s, body);
+    s.push('《');
+    write_escaped(s, ruby);
+    s.push('》');
+}
+
+fn needs_directive(s: &str, body: &str) -> bool {
+    !body.chars().all(character::is_kanji_related)
+        || s.chars().last().is_some_and(character::is_kanji_related)
+}
+
+fn flatten(text: &TokenText, escaped: bool) -> String {
+    let mut s = String::new();
+    for token in text.iter() {
+        match token {
+            Token::Term { body, .. }
+            | Token::Ruby { body, .. }
+            | Token::KanjiRuby { body, .. }
+            | Token::MonoRuby { body, .. }
+            | Token::Escape { body, .. }
+            | Token::Spase(body)
+            | Token::Plaintext(body) => {
+                if escaped {
+                    write_escaped(&mut s, body.body())
+                } else {
+                    s.push_str(body.body())
+                }
+            }
+            Token::Annotation {
+                body, description, ..
+            } if escaped && flatten(body, false) == GAIJI_MARK => {
+                write!(s, "{}［＃{}］", GAIJI_MARK, flatten(description, true)).unwrap()
+            }
+            Token::Annotation { body, .. } | Token::EmphasisMark { body, .. } => {
+                s.push_str(&flatten(body, escaped))
+            }
+            Token::Ignore(_) | Token::NewLine(_) => {}
+        }
+    }
+    s
+}
+
+fn write_escaped(s: &mut String, input: &str) {
+    for c in input.chars() {
+        match ESCAPES.iter().find(|(escaped, _)| *escaped == c) {
+            Some((_, notation)) => s.push_str(notation),
+            None => s.push(c),
+        }
+    }
+}
+
+pub fn unescape(input: &str) -> String {
+    let mut s = String::new();
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        match ESCAPES
+            .iter()
+            .find(|(_, notation)| rest.starts_with(notation))
+        {
+            Some((escaped, notation)) => {
+                s.push(*escaped);
+                rest = &rest[notation.len()..];
+            }
+            None => {
+                s.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::token::dialect;
+    use crate::parser::token::test_helper::{new_test_context, new_test_text};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    fn parse(input: &str, dialect: Arc<dyn dialect::Dialect>) -> TokenText {
+        let terms = vec![crate::term::Term::new(
+            crate::Id::new("term_id1"),
+            "穂積".into(),
+            "ほづみ".into(),
+            "".into(),
+            false,
+        )];
+        new_test_text(&new_test_context(terms).with_dialect(dialect), input)
+    }
+
+    #[test_case("|ほげ《ふが》"=>"｜ほげ《ふが》";"ruby")]
+    #[test_case("漢字(かんじ)"=>"漢字《かんじ》";"kanji_ruby")]
+    #[test_case("本|漢字《かんじ》"=>"本｜漢字《かんじ》";"ruby_after_kanji")]
+    #[test_case("漢字(かん・じ)"=>"漢《かん》字《じ》";"mono_ruby")]
+    #[test_case("あ|漢字《かんじ》"=>"あ漢字《かんじ》";"ruby_after_kana")]
+    #[test_case("《《傍点》》です"=>"傍点［＃「傍点」に傍点］です";"emphasis_mark")]
+    #[test_case("《《白ゴマ|傍点》》"=>"傍点［＃「傍点」に白ゴマ傍点］";"emphasis_mark_style")]
+    #[test_case("|漢字$せつめい$"=>"漢字［＃「漢字」に「せつめい」の注記］";"annotation")]
+    #[test_case("\"穂積\"さん\n"=>"穂積さん\r\n";"term")]
+    #[test_case("|注$説明［1］$"=>"注［＃「注」に「説明※［＃始め角括弧、1-1-46］1※［＃終わり角括弧、1-1-47］」の注記］";"annotation_escape")]
+    #[test_case("《《注［1］》》"=>"［＃傍点］注※［＃始め角括弧、1-1-46］1※［＃終わり角括弧、1-1-47］［＃傍点終わり］";"emphasis_mark_escape")]
+    #[test_case("［注］《"=>"※［＃始め角括弧、1-1-46］注※［＃終わり角括弧、1-1-47］※［＃始め二重山括弧、1-1-52］";"escape")]
+    fn export_works(input: &str) -> String {
+        export(&parse(input, Arc::new(dialect::NovelArchives)))
+    }
+
+    #[test_case("それは本文［＃「本文」に傍点］だ")]
+    #[test_case("それは本文［＃「本文」に白丸傍点］だ")]
+    #[test_case("２０［＃「２０」は縦中横］")]
+    #[test_case("［＃改ページ］\r\n")]
+    #[test_case("※［＃「木＋吶のつくり」、第3水準1-85-54］")]
+    #[test_case("本｜青空《あおぞら》と漢字《かんじ》")]
+    #[test_case("［＃※［＃始め角括弧、1-1-46］注記※［＃終わり角括弧、1-1-47］］")]
+    #[test_case("本文［＃「本文」に「※［＃始め二重山括弧、1-1-52］注」の注記］")]
+    #[test_case("［＃傍点］※［＃始め角括弧、1-1-46］注［＃傍点終わり］")]
+    fn export_aozora_round_trip_works(input: &str) {
+        assert_eq!(export(&parse(input, Arc::new(dialect::Aozora))), input);
+    }
+
+    #[test_case("|［注］《ちゅう》"=>vec![("［注］".to_string(), "ちゅう".to_string())];"ruby_body")]
+    #[test_case("|※印《こめ｜じるし》"=>vec![("※印".to_string(), "こめ｜じるし".to_string())];"ruby")]
+    #[test_case("漢字(か［ん］じ)"=>vec![("漢字".to_string(), "か［ん］じ".to_string())];"kanji_ruby")]
+    fn export_escaped_ruby_round_trip_works(input: &str) -> Vec<(String, String)> {
+        let exported = export(&parse(input, Arc::new(dialect::NovelArchives)));
+        parse(&exported, Arc::new(dialect::Aozora))
+            .iter()
+            .filter_map(|token| match token {
+                Token::Ruby { body, ruby, .. } | Token::KanjiRuby { body, ruby, .. } => {
+                    Some((unescape(body.body()), unescape(ruby.body())))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test_case("［注］《※｜"=>"［注］《※｜";"all")]
+    #[test_case("※［＃始め角括弧、1-1-46］"=>"※［＃始め角括弧、1-1-46］";"notation")]
+    fn unescape_works(input: &str) -> String {
+        let mut s = String::new();
+        write_escaped(&mut s, input);
+        unescape(&s)
+    }
+}
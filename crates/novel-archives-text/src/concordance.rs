@@ -0,0 +1,279 @@
+use super::*;
+use parser::token::ParseContext;
+use std::collections::BTreeMap;
+use term::Term;
+
+const SNIPPET_CONTEXT_CHAR_COUNT: usize = 10;
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct Occurrence {
+    chapter_id: String,
+    position: Position,
+    body: String,
+    snippet: String,
+    quoted: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Concordance {
+    chapter_ids: Vec<String>,
+    occurrences: BTreeMap<String, Vec<Occurrence>>,
+}
+
+impl Concordance {
+    pub fn new<'c, 'a: 'c>(
+        context: &ParseContext,
+        chapters: impl IntoIterator<Item = (&'c str, &'c TokenText<'a>)>,
+    ) -> Self {
+        let mut concordance = Self::default();
+        for (chapter_id, text) in chapters {
+            let mut found = vec![];
+            let mut line = Line::default();
+            index_text(context, text, &mut line, &mut found);
+            line.finish(&mut found);
+            found.sort_by_key(|(_, hit)| *hit.position.byte_offset());
+            for (term_id, hit) in found {
+                concordance
+                    .occurrences
+                    .entry(term_id)
+                    .or_default()
+                    .push(hit.into_occurrence(chapter_id));
+            }
+            concordance.chapter_ids.push(chapter_id.to_string());
+        }
+        concordance
+    }
+
+    pub fn occurrences(&self, term_id: &Id<Term>) -> &[Occurrence] {
+        self.occurrences
+            .get(term_id.value())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn first_appearance(&self, term_id: &Id<Term>) -> Option<&Occurrence> {
+        self.occurrences(term_id).first()
+    }
+
+    pub fn first_appearances(&self) -> Vec<(Id<Term>, &Occurrence)> {
+        let mut first_appearances: Vec<_> = self
+            .occurrences
+            .iter()
+            .filter_map(|(term_id, occurrences)| {
+                Some((Id::new(term_id.clone()), occurrences.first()?))
+            })
+            .collect();
+        first_appearances.sort_by_key(|(_, occurrence)| {
+            (
+                self.chapter_ids
+                    .iter()
+                    .position(|id| *id == occurrence.chapter_id),
+                *occurrence.position.byte_offset(),
+            )
+        });
+        first_appearances
+    }
+}
+
+#[derive(Debug)]
+struct Hit {
+    position: Position,
+    body: String,
+    quoted: bool,
+    char_offset: usize,
+    char_count: usize,
+    snippet: String,
+}
+
+impl Hit {
+    fn into_occurrence(self, chapter_id: &str) -> Occurrence {
+        Occurrence::new(
+            chapter_id.to_string(),
+            self.position,
+            self.body,
+            self.snippet,
+            self.quoted,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Line {
+    text: String,
+    char_count: usize,
+    hits: Vec<(String, Hit)>,
+}
+
+impl Line {
+    fn push_text(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.char_count += text.chars().count();
+    }
+
+    fn push_hit(&mut self, term_id: &Id<Term>, position: Position, body: &str, quoted: bool) {
+        self.hits.push((
+            term_id.value().clone(),
+            Hit {
+                position,
+                body: body.to_string(),
+                quoted,
+                char_offset: self.char_count,
+                char_count: body.chars().count(),
+                snippet: String::new(),
+            },
+        ));
+    }
+
+    fn finish(&mut self, found: &mut Vec<(String, Hit)>) {
+        let chars: Vec<char> = self.text.chars().collect();
+        for (term_id, mut hit) in self.hits.drain(..) {
+            let start = hit.char_offset.saturating_sub(SNIPPET_CONTEXT_CHAR_COUNT);
+            let end =
+                (hit.char_offset + hit.char_count + SNIPPET_CONTEXT_CHAR_COUNT).min(chars.len());
+            hit.snippet = chars[start..end].iter().collect();
+            found.push((term_id, hit));
+        }
+        self.text.clear();
+        self.char_count = 0;
+    }
+}
+
+fn index_text(
+    context: &ParseContext,
+    text: &TokenText,
+    line: &mut Line,
+    found: &mut Vec<(String, Hit)>,
+) {
+    for token in text.iter() {
+        match token {
+            Token::Term {
+                body,
+                term_id,
+                delimiter,
+            } => {
+                let quoted = !delimiter.start().is_empty();
+                line.push_hit(
+                    term_id,
+                    body.original_position().clone(),
+                    body.body(),
+                    quoted,
+                );
+                line.push_text(body.body());
+            }
+            Token::Ruby { body, .. }
+            | Token::KanjiRuby { body, .. }
+            | Token::MonoRuby { body, .. }
+            | Token::Plaintext(body) => index_surfaces(context, body, line),
+            Token::EmphasisMark { body, .. } => index_text(context, body, line, found),
+            Token::Annotation {
+                body, description, ..
+            } => {
+                index_text(context, body, line, found);
+                let mut description_line = Line::default();
+                index_text(context, description, &mut description_line, found);
+                description_line.finish(found);
+            }
+            Token::Escape { body, .. } | Token::Spase(body) => line.push_text(body.body()),
+            Token::Ignore(_) => {}
+            Token::NewLine(_) => line.finish(found),
+        }
+    }
+}
+
+fn index_surfaces(context: &ParseContext, span: &Span, line: &mut Line) {
+    let body = span.body();
+    let mut indexed = 0;
+    for (offset, len, term) in context.term_matcher().find_iter(body) {
+        line.push_text(&body[indexed..offset]);
+        let position =
+            Span::new(body[..offset].into(), span.original_position().clone()).end_position();
+        line.push_hit(term.id(), position, &body[offset..offset + len], false);
+        line.push_text(&body[offset..offset + len]);
+        indexed = offset + len;
+    }
+    line.push_text(&body[indexed..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::token::test_helper::{new_test_context, new_test_text};
+    use test_case::test_case;
+
+    fn context() -> ParseContext {
+        let terms = vec![
+            Term::new(
+                Id::new("hozumi"),
+                "穂積".into(),
+                "ほづみ".into(),
+                "".into(),
+                false,
+            ),
+            Term::new(
+                Id::new("library"),
+                "王立図書館".into(),
+                "おうりつとしょかん".into(),
+                "".into(),
+                false,
+            ),
+        ];
+        new_test_context(terms)
+    }
+
+    fn concordance(chapters: &[(&str, &str)]) -> Concordance {
+        let context = context();
+        let texts: Vec<TokenText> = chapters
+            .iter()
+            .map(|(_, input)| new_test_text(&context, input))
+            .collect();
+        Concordance::new(
+            &context,
+            chapters.iter().map(|(id, _)| *id).zip(texts.iter()),
+        )
+    }
+
+    #[test_case(&[("1", "\"穂積\"は王立図書館へ行った。")], "hozumi"=>vec![
+        ("1".into(), 1, 1, "穂積は王立図書館へ行った".into(), true),
+    ];"quoted")]
+    #[test_case(&[("1", "\"穂積\"は王立図書館へ行った。")], "library"=>vec![
+        ("1".into(), 1, 11, "穂積は王立図書館へ行った。".into(), false),
+    ];"unquoted")]
+    #[test_case(&[("1", "序章\n|王立図書館(おうりつとしょかん)"), ("2", "|注$王立図書館$")], "library"=>vec![
+        ("1".into(), 2, 8, "王立図書館".into(), false),
+        ("2".into(), 1, 5, "王立図書館".into(), false),
+    ];"ruby_and_annotation")]
+    #[test_case(&[("1", "それは、とても長い長い物語の始まりだった。\"穂積\"は目を覚ました。")], "hozumi"=>vec![
+        ("1".into(), 1, 64, "物語の始まりだった。穂積は目を覚ました。".into(), true),
+    ];"snippet")]
+    fn occurrences_works(
+        chapters: &[(&str, &str)],
+        term_id: &str,
+    ) -> Vec<(String, usize, usize, String, bool)> {
+        concordance(chapters)
+            .occurrences(&Id::new(term_id))
+            .iter()
+            .map(|occurrence| {
+                (
+                    occurrence.chapter_id().clone(),
+                    *occurrence.position().line(),
+                    *occurrence.position().byte_offset(),
+                    occurrence.snippet().clone(),
+                    *occurrence.quoted(),
+                )
+            })
+            .collect()
+    }
+
+    #[test_case(&[("1", "王立図書館"), ("2", "\"穂積\"と王立図書館")]=>vec![
+        ("library".into(), "1".into()),
+        ("hozumi".into(), "2".into()),
+    ];"reading_order")]
+    #[test_case(&[("1", "なにもない")]=>Vec::<(String, String)>::new();"empty")]
+    fn first_appearances_works(chapters: &[(&str, &str)]) -> Vec<(String, String)> {
+        concordance(chapters)
+            .first_appearances()
+            .into_iter()
+            .map(|(term_id, occurrence)| (term_id.value().clone(), occurrence.chapter_id().clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,194 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Clone, Getters)]
+pub struct CountRules {
+    body: bool,
+    ruby: bool,
+    spaces: bool,
+    newlines: bool,
+    directives: bool,
+    annotation_descriptions: bool,
+}
+
+impl Default for CountRules {
+    fn default() -> Self {
+        Self {
+            body: true,
+            ruby: false,
+            spaces: true,
+            newlines: false,
+            directives: false,
+            annotation_descriptions: false,
+        }
+    }
+}
+
+impl CountRules {
+    /// Counts plaintext, terms, escaped characters, ruby bodies, emphasis marks,
+    /// annotation bodies and spaces.
+    pub fn kakuyomu() -> Self {
+        Self::default()
+    }
+
+    /// Counts the same tokens as [`CountRules::kakuyomu`] except spaces.
+    pub fn narou() -> Self {
+        Self::default().with_spaces(false)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "kakuyomu" => Some(Self::kakuyomu()),
+            "narou" => Some(Self::narou()),
+            _ => None,
+        }
+    }
+
+    pub fn with_body(self, body: bool) -> Self {
+        Self { body, ..self }
+    }
+
+    pub fn with_ruby(self, ruby: bool) -> Self {
+        Self { ruby, ..self }
+    }
+
+    pub fn with_spaces(self, spaces: bool) -> Self {
+        Self { spaces, ..self }
+    }
+
+    pub fn with_newlines(self, newlines: bool) -> Self {
+        Self { newlines, ..self }
+    }
+
+    pub fn with_directives(self, directives: bool) -> Self {
+        Self { directives, ..self }
+    }
+
+    pub fn with_annotation_descriptions(self, annotation_descriptions: bool) -> Self {
+        Self {
+            annotation_descriptions,
+            ..self
+        }
+    }
+}
+
+pub fn count_chars(text: &TokenText, rules: &CountRules) -> usize {
+    text.iter().map(|token| count_token(token, rules)).sum()
+}
+
+fn count_token(token: &Token, rules: &CountRules) -> usize {
+    let count = |counted: bool, body: &str| if counted { body.chars().count() } else { 0 };
+    match token {
+        Token::Ruby {
+            directive,
+            body,
+            ruby,
+            ..
+        } => {
+            count(rules.directives, directive)
+                + count(rules.body, body.body())
+                + count(rules.ruby, ruby.body())
+        }
+        Token::KanjiRuby { body, ruby, .. } => {
+            count(rules.body, body.body()) + count(rules.ruby, ruby.body())
+        }
+        Token::MonoRuby { body, pairs, .. } => {
+            count(rules.body, body.body())
+                + pairs
+                    .iter()
+                    .map(|pair| count(rules.ruby, pair.ruby().body()))
+                    .sum::<usize>()
+        }
+        Token::Annotation {
+            directive,
+            body,
+            description,
+            ..
+        } => {
+            count(rules.directives, directive)
+                + count_chars(body, rules)
+                + if rules.annotation_descriptions {
+                    count_chars(description, rules)
+                } else {
+                    0
+                }
+        }
+        Token::Escape { directive, body } => {
+            count(rules.directives, directive) + count(rules.body, body.body())
+        }
+        Token::EmphasisMark { body, .. } => count_chars(body, rules),
+        Token::Term { body, .. } | Token::Plaintext(body) => count(rules.body, body.body()),
+        Token::Spase(body) => count(rules.spaces, body.body()),
+        Token::NewLine(_) => usize::from(rules.newlines),
+        Token::Ignore(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::token;
+    use test_case::test_case;
+
+    fn parse(input: &str) -> TokenText {
+        token::iterator::TextIterator::new(
+            token::ParseContext::default(),
+            token::ParsedSpan::new(input),
+        )
+        .collect()
+    }
+
+    #[test_case("", CountRules::default()=>0;"empty")]
+    #[test_case("本文です", CountRules::default()=>4;"plaintext")]
+    #[test_case("|漢字(かんじ)", CountRules::default()=>2;"ruby")]
+    #[test_case("|漢字(かんじ)", CountRules::default().with_ruby(true)=>5;"with_ruby")]
+    #[test_case("|漢字(かんじ)", CountRules::default().with_body(false).with_ruby(true)=>3;"ruby_only")]
+    #[test_case("|漢字(かんじ)", CountRules::default().with_directives(true)=>3;"with_directives")]
+    #[test_case("本文です", CountRules::default().with_body(false)=>0;"plaintext_without_body")]
+    #[test_case("本文 ||《《強調》》", CountRules::default().with_body(false)=>1;"without_body")]
+    #[test_case("本文|漢字(かんじ)", CountRules::default().with_body(false).with_ruby(true)=>3;"plaintext_and_ruby_only")]
+    #[test_case("漢字(かん・じ)", CountRules::default().with_ruby(true)=>5;"mono_ruby")]
+    #[test_case("《《強調》》", CountRules::default()=>2;"emphasis_mark")]
+    #[test_case("《《a|》》》", CountRules::default()=>2;"emphasis_mark_escape")]
+    #[test_case("《《a|》》》", CountRules::default().with_directives(true)=>3;"emphasis_mark_escape_with_directives")]
+    #[test_case("\"穂積\"", CountRules::default()=>4;"unknown_term")]
+    #[test_case("||", CountRules::default()=>1;"escape")]
+    #[test_case("||", CountRules::default().with_directives(true)=>2;"escape_with_directives")]
+    #[test_case("|(かっこ)", CountRules::default()=>5;"escape_ruby_start")]
+    #[test_case("|注$説明$", CountRules::default()=>1;"annotation")]
+    #[test_case("|注$説明$", CountRules::default().with_annotation_descriptions(true)=>3;"with_annotation_descriptions")]
+    #[test_case("|注$a|$b$", CountRules::default().with_annotation_descriptions(true)=>4;"annotation_escape")]
+    #[test_case("　あ い\nう\n", CountRules::kakuyomu()=>5;"kakuyomu")]
+    #[test_case("　あ い\nう\n", CountRules::narou()=>3;"narou")]
+    #[test_case("　あ い\nう\n", CountRules::narou().with_newlines(true)=>5;"with_newlines")]
+    fn count_chars_works(input: &str, rules: CountRules) -> usize {
+        count_chars(&parse(input), &rules)
+    }
+
+    #[test_case("|(かっこ)", CountRules::default()=>5;"ignore")]
+    #[test_case("|(かっこ)", CountRules::default().with_directives(true)=>5;"ignore_with_directives")]
+    fn count_chars_without_escape_works(input: &str, rules: CountRules) -> usize {
+        let context = token::ParseContext::default()
+            .with_options(token::ParserOptions::default().with_escape(false));
+        let text: TokenText =
+            token::iterator::TextIterator::new(context, token::ParsedSpan::new(input)).collect();
+        assert!(matches!(text.first(), Some(Token::Ignore(_))));
+        count_chars(&text, &rules)
+    }
+
+    #[test]
+    fn count_chars_term_works() {
+        let text = TokenText::new(vec![Token::new_term(
+            Span::new("穂積".into(), Position::new(1, 1)),
+            Id::new("hozumi"),
+            Delimiter::new("\"".into(), "\"".into()),
+        )]);
+        assert_eq!(count_chars(&text, &CountRules::default()), 2);
+    }
+
+    #[test_case("kakuyomu"=>Some(CountRules::kakuyomu());"kakuyomu")]
+    #[test_case("narou"=>Some(CountRules::narou());"narou")]
+    #[test_case("unknown"=>None;"unknown")]
+    fn from_name_works(name: &str) -> Option<CountRules> {
+        CountRules::from_name(name)
+    }
+}
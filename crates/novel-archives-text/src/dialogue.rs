@@ -0,0 +1,267 @@
+use super::*;
+use counting::CountRules;
+use parser::{Diagnostic, DiagnosticKind};
+use std::borrow::Cow;
+
+const DIALOGUE_BRACKETS: [(char, char); 2] = [('「', '」'), ('『', '』')];
+
+#[derive(Debug, PartialEq, Clone, new)]
+pub enum Segment<'a> {
+    Narration(TokenText<'a>),
+    Dialogue {
+        body: Vec<Segment<'a>>,
+        delimiter: Delimiter<'a>,
+        position: Position,
+    },
+}
+
+impl<'a> Segment<'a> {
+    pub fn char_count(&self) -> usize {
+        match self {
+            Segment::Narration(text) => counting::count_chars(text, &CountRules::default()),
+            Segment::Dialogue { body, .. } => body.iter().map(Segment::char_count).sum(),
+        }
+    }
+}
+
+pub fn split<'a>(text: &TokenText<'a>) -> (Vec<Segment<'a>>, Vec<Diagnostic>) {
+    let mut diagnostics = vec![];
+    let mut stack = vec![Frame::default()];
+    for token in text.iter() {
+        let plaintext = match token {
+            Token::Plaintext(plaintext) => plaintext,
+            token => {
+                top(&mut stack).narration.push(token.clone());
+                continue;
+            }
+        };
+        let mut rest = plaintext.clone();
+        while let Some((offset, c)) = rest.body().char_indices().find(|(_, c)| is_bracket(*c)) {
+            let (before, bracket, after) = split_span(&rest, offset, c.len_utf8());
+            if !before.body().is_empty() {
+                top(&mut stack).narration.push(Token::Plaintext(before));
+            }
+            if is_start_dialogue(c) {
+                stack.push(Frame::new(Some(bracket)));
+            } else if stack.len() > 1 && top(&mut stack).is_closed_by(c) {
+                let frame = stack.pop().unwrap();
+                top(&mut stack).close(frame, bracket);
+            } else {
+                diagnostics.push(Diagnostic::from_span(
+                    DiagnosticKind::UnbalancedDialogue,
+                    bracket.clone(),
+                ));
+                top(&mut stack).narration.push(Token::Plaintext(bracket));
+            }
+            rest = after;
+        }
+        if !rest.body().is_empty() {
+            top(&mut stack).narration.push(Token::Plaintext(rest));
+        }
+    }
+    while stack.len() > 1 {
+        let frame = stack.pop().unwrap();
+        let start = frame.start.clone().unwrap();
+        diagnostics.push(Diagnostic::from_span(
+            DiagnosticKind::UnbalancedDialogue,
+            start.clone(),
+        ));
+        let parent = top(&mut stack);
+        parent.narration.push(Token::Plaintext(start));
+        for segment in frame.into_segments() {
+            parent.append(segment);
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| *diagnostic.span().original_position().byte_offset());
+    (stack.pop().unwrap().into_segments(), diagnostics)
+}
+
+pub fn dialogue_ratio(segments: &[Segment]) -> f64 {
+    let (dialogue, total) =
+        segments
+            .iter()
+            .fold((0, 0), |(dialogue, total), segment| match segment {
+                Segment::Narration(_) => (dialogue, total + segment.char_count()),
+                Segment::Dialogue { .. } => (
+                    dialogue + segment.char_count(),
+                    total + segment.char_count(),
+                ),
+            });
+    if total == 0 {
+        0.0
+    } else {
+        dialogue as f64 / total as f64
+    }
+}
+
+#[derive(Debug, Default, new)]
+struct Frame<'a> {
+    start: Option<Span<'a>>,
+    #[new(default)]
+    segments: Vec<Segment<'a>>,
+    #[new(default)]
+    narration: Vec<Token<'a>>,
+}
+
+impl<'a> Frame<'a> {
+    fn is_closed_by(&self, c: char) -> bool {
+        self.start.as_ref().is_some_and(|start| {
+            DIALOGUE_BRACKETS
+                .iter()
+                .any(|(open, close)| *close == c && start.body().starts_with(*open))
+        })
+    }
+
+    fn flush(&mut self) {
+        if !self.narration.is_empty() {
+            let narration = std::mem::take(&mut self.narration);
+            self.segments
+                .push(Segment::Narration(TokenText::new(narration)));
+        }
+    }
+
+    fn append(&mut self, segment: Segment<'a>) {
+        match segment {
+            Segment::Narration(text) => self.narration.extend(text.iter().cloned()),
+            dialogue => {
+                self.flush();
+                self.segments.push(dialogue);
+            }
+        }
+    }
+
+    fn close(&mut self, frame: Frame<'a>, end: Span<'a>) {
+        let start = frame.start.clone().unwrap();
+        let position = start.original_position().clone();
+        self.append(Segment::new_dialogue(
+            frame.into_segments(),
+            Delimiter::new(start.body().clone(), end.body().clone()),
+            position,
+        ));
+    }
+
+    fn into_segments(mut self) -> Vec<Segment<'a>> {
+        self.flush();
+        self.segments
+    }
+}
+
+fn top<'a, 'b>(stack: &'b mut [Frame<'a>]) -> &'b mut Frame<'a> {
+    stack.last_mut().unwrap()
+}
+
+fn is_start_dialogue(c: char) -> bool {
+    DIALOGUE_BRACKETS.iter().any(|(open, _)| *open == c)
+}
+
+fn is_bracket(c: char) -> bool {
+    DIALOGUE_BRACKETS
+        .iter()
+        .any(|(open, close)| *open == c || *close == c)
+}
+
+fn split_span<'a>(span: &Span<'a>, offset: usize, len: usize) -> (Span<'a>, Span<'a>, Span<'a>) {
+    let slice = |range: std::ops::Range<usize>| match span.body() {
+        Cow::Borrowed(body) => Cow::Borrowed(&body[range]),
+        Cow::Owned(body) => Cow::Owned(body[range].to_string()),
+    };
+    let at = |offset: usize| {
+        Span::new(slice(0..offset), span.original_position().clone()).end_position()
+    };
+    let body_len = span.body().len();
+    (
+        Span::new(slice(0..offset), span.original_position().clone()),
+        Span::new(slice(offset..offset + len), at(offset)),
+        Span::new(slice(offset + len..body_len), at(offset + len)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::token;
+    use test_case::test_case;
+
+    fn parse(input: &str) -> TokenText {
+        token::iterator::TextIterator::new(
+            token::ParseContext::default(),
+            token::ParsedSpan::new(input),
+        )
+        .collect()
+    }
+
+    fn position(byte_offset: usize) -> Position {
+        let column = byte_offset / '「'.len_utf8() + 1;
+        Position::new(1, byte_offset).with_columns(column, column, column)
+    }
+
+    fn narration(body: &str, byte_offset: usize) -> Segment {
+        Segment::new_narration(TokenText::new(vec![Token::new_plaintext(Span::new(
+            body.into(),
+            position(byte_offset),
+        ))]))
+    }
+
+    fn dialogue(
+        body: Vec<Segment<'static>>,
+        start: &'static str,
+        end: &'static str,
+        byte_offset: usize,
+    ) -> Segment<'static> {
+        Segment::new_dialogue(
+            body,
+            Delimiter::new(start.into(), end.into()),
+            position(byte_offset),
+        )
+    }
+
+    #[test_case("ほげ"=>vec![narration("ほげ", 0)];"narration")]
+    #[test_case("あ「いう」え"=>vec![
+        narration("あ", 0),
+        dialogue(vec![narration("いう", 6)], "「", "」", 3),
+        narration("え", 15),
+    ];"dialogue")]
+    #[test_case("「あ『い』う」"=>vec![
+        dialogue(vec![
+            narration("あ", 3),
+            dialogue(vec![narration("い", 9)], "『", "』", 6),
+            narration("う", 15),
+        ], "「", "」", 0),
+    ];"nested")]
+    #[test_case("「」"=>vec![dialogue(vec![], "「", "」", 0)];"empty")]
+    #[test_case("「あ』"=>vec![
+        Segment::new_narration(TokenText::new(vec![
+            Token::new_plaintext(Span::new("「".into(), position(0))),
+            Token::new_plaintext(Span::new("あ".into(), position(3))),
+            Token::new_plaintext(Span::new("』".into(), position(6))),
+        ])),
+    ];"mismatched")]
+    fn split_works(input: &str) -> Vec<Segment> {
+        split(&parse(input)).0
+    }
+
+    #[test_case("「あ」"=>Vec::<(String, usize)>::new();"balanced")]
+    #[test_case("あ」"=>vec![("」".into(), 3)];"unexpected_end")]
+    #[test_case("「あ『い」"=>vec![("「".into(), 0), ("『".into(), 6), ("」".into(), 12)];"unclosed")]
+    fn split_diagnostics_works(input: &str) -> Vec<(String, usize)> {
+        split(&parse(input))
+            .1
+            .into_iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.kind(), &DiagnosticKind::UnbalancedDialogue);
+                (
+                    diagnostic.span().body().to_string(),
+                    *diagnostic.span().original_position().byte_offset(),
+                )
+            })
+            .collect()
+    }
+
+    #[test_case(""=>0.0;"empty")]
+    #[test_case("あいう"=>0.0;"narration")]
+    #[test_case("「あいう」"=>1.0;"dialogue")]
+    #[test_case("「あ」いう"=>1.0 / 3.0;"mixed")]
+    fn dialogue_ratio_works(input: &str) -> f64 {
+        dialogue_ratio(&split(&parse(input)).0)
+    }
+}
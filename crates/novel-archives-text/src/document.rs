@@ -0,0 +1,272 @@
+use super::*;
+use parser::nom_extend::character;
+use std::ops::Deref;
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_HEADING_LEVEL: usize = 6;
+
+#[derive(Debug, PartialEq, Clone, new)]
+pub struct Document<'a>(Vec<Block<'a>>);
+
+impl<'a> Deref for Document<'a> {
+    type Target = Vec<Block<'a>>;
+    fn deref(&self) -> &<Self as std::ops::Deref>::Target {
+        &self.0
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, new)]
+pub enum Block<'a> {
+    Paragraph {
+        indent: usize,
+        body: TokenText<'a>,
+        position: Position,
+    },
+    Heading {
+        level: usize,
+        body: TokenText<'a>,
+        position: Position,
+    },
+    SceneBreak(Span<'a>),
+    BlankLine {
+        count: usize,
+        position: Position,
+    },
+}
+
+impl<'a> Block<'a> {
+    pub fn position(&self) -> &Position {
+        match self {
+            Block::Paragraph { position, .. }
+            | Block::Heading { position, .. }
+            | Block::BlankLine { position, .. } => position,
+            Block::SceneBreak(span) => span.original_position(),
+        }
+    }
+
+    pub fn dialogue(&self) -> Vec<dialogue::Segment<'a>> {
+        match self {
+            Block::Paragraph { body, .. } | Block::Heading { body, .. } => dialogue::split(body).0,
+            Block::SceneBreak(_) | Block::BlankLine { .. } => vec![],
+        }
+    }
+
+    pub fn dialogue_ratio(&self) -> f64 {
+        dialogue::dialogue_ratio(&self.dialogue())
+    }
+}
+
+impl<'a> From<&TokenText<'a>> for Document<'a> {
+    fn from(text: &TokenText<'a>) -> Self {
+        let mut blocks: Vec<Block> = vec![];
+        for (tokens, position) in lines(text) {
+            let block = match (blocks.last_mut(), block(tokens, position)) {
+                (
+                    Some(Block::BlankLine { count, .. }),
+                    Block::BlankLine {
+                        count: additional, ..
+                    },
+                ) => {
+                    *count += additional;
+                    continue;
+                }
+                (_, block) => block,
+            };
+            blocks.push(block);
+        }
+        Document::new(blocks)
+    }
+}
+
+fn lines<'a, 'b>(text: &'b TokenText<'a>) -> Vec<(&'b [Token<'a>], Position)> {
+    let mut lines = vec![];
+    let mut start = 0;
+    for (index, token) in text.iter().enumerate() {
+        if let Token::NewLine(newline) = token {
+            let position = text[start..index]
+                .first()
+                .and_then(token_position)
+                .unwrap_or_else(|| newline.original_position().clone());
+            lines.push((&text[start..index], position));
+            start = index + 1;
+        }
+    }
+    if let Some(position) = text[start..].first().and_then(token_position) {
+        lines.push((&text[start..], position));
+    }
+    lines
+}
+
+fn block<'a>(tokens: &[Token<'a>], position: Position) -> Block<'a> {
+    if tokens.iter().all(|token| matches!(token, Token::Spase(_))) {
+        return Block::new_blank_line(1, position);
+    }
+    if let Some(scene_break) = scene_break(tokens) {
+        return Block::new_scene_break(Span::new(scene_break.into(), position));
+    }
+    if let Some(level) = heading_level(tokens) {
+        return Block::new_heading(level, TokenText::new(tokens[2..].to_vec()), position);
+    }
+    let indent_len = tokens
+        .iter()
+        .take_while(|token| matches!(token, Token::Spase(_)))
+        .count();
+    let indent = tokens[..indent_len]
+        .iter()
+        .map(|token| match token {
+            Token::Spase(space) => space.body().chars().count(),
+            _ => 0,
+        })
+        .sum();
+    Block::new_paragraph(
+        indent,
+        TokenText::new(tokens[indent_len..].to_vec()),
+        position,
+    )
+}
+
+fn scene_break(tokens: &[Token]) -> Option<String> {
+    let mut body = String::new();
+    for token in tokens {
+        match token {
+            Token::Spase(space) => body.push_str(space.body()),
+            Token::Plaintext(plaintext) if plaintext.body().chars().all(is_scene_break_mark) => {
+                body.push_str(plaintext.body())
+            }
+            _ => return None,
+        }
+    }
+    let body = body.trim_matches(character::is_any_space);
+    Some(body.to_string()).filter(|body| !body.is_empty())
+}
+
+fn heading_level(tokens: &[Token]) -> Option<usize> {
+    match tokens {
+        [Token::Plaintext(marks), Token::Spase(_), _, ..] => {
+            let level = marks.body().chars().count();
+            (level <= MAX_HEADING_LEVEL && marks.body().chars().all(|c| c == '#' || c == '＃'))
+                .then_some(level)
+        }
+        _ => None,
+    }
+}
+
+fn is_scene_break_mark(c: char) -> bool {
+    matches!(c, '＊' | '*' | '◇' | '◆' | '☆' | '★' | '※')
+}
+
+fn token_position(token: &Token) -> Option<Position> {
+    match token {
+        Token::Term {
+            body, delimiter, ..
+        } => Some(shift_back(body.original_position(), delimiter.start())),
+        Token::KanjiRuby { body, .. }
+        | Token::MonoRuby { body, .. }
+        | Token::Spase(body)
+        | Token::Ignore(body)
+        | Token::Plaintext(body)
+        | Token::NewLine(body) => Some(body.original_position().clone()),
+        Token::Ruby {
+            directive, body, ..
+        }
+        | Token::Escape { directive, body } => {
+            Some(shift_back(body.original_position(), directive))
+        }
+        Token::Annotation {
+            directive, body, ..
+        } => body
+            .first()
+            .and_then(token_position)
+            .map(|position| shift_back(&position, directive)),
+        Token::EmphasisMark {
+            body, delimiter, ..
+        } => body
+            .first()
+            .and_then(token_position)
+            .map(|position| shift_back(&position, delimiter.start())),
+    }
+}
+
+fn shift_back(position: &Position, prefix: &str) -> Position {
+    Position::new(
+        *position.line(),
+        position.byte_offset().saturating_sub(prefix.len()),
+    )
+    .with_columns(
+        position.column().saturating_sub(prefix.chars().count()),
+        position
+            .grapheme_column()
+            .saturating_sub(prefix.graphemes(true).count()),
+        position
+            .utf16_column()
+            .saturating_sub(prefix.encode_utf16().count()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::token;
+    use test_case::test_case;
+
+    fn parse(input: &str) -> Document {
+        let text: TokenText = token::iterator::TextIterator::new(
+            token::ParseContext::default(),
+            token::ParsedSpan::new(input),
+        )
+        .collect();
+        Document::from(&text)
+    }
+
+    fn position(line: usize, byte_offset: usize, column: usize) -> Position {
+        Position::new(line, byte_offset).with_columns(column, column, column)
+    }
+
+    fn plaintext(body: &str, line: usize, byte_offset: usize, column: usize) -> TokenText {
+        TokenText::new(vec![Token::new_plaintext(Span::new(
+            body.into(),
+            position(line, byte_offset, column),
+        ))])
+    }
+
+    #[test_case("　ほんぶん\n\n\n＊＊＊\n# みだし"=>Document::new(vec![
+        Block::new_paragraph(1, plaintext("ほんぶん", 1, 3, 2), position(1, 0, 1)),
+        Block::new_blank_line(2, position(2, 16, 1)),
+        Block::new_scene_break(Span::new("＊＊＊".into(), position(4, 18, 1))),
+        Block::new_heading(1, plaintext("みだし", 5, 30, 3), position(5, 28, 1)),
+    ]);"blocks")]
+    #[test_case("ほげ\r\n  ふが\r\n"=>Document::new(vec![
+        Block::new_paragraph(0, plaintext("ほげ", 1, 0, 1), position(1, 0, 1)),
+        Block::new_paragraph(2, plaintext("ふが", 2, 10, 3), position(2, 8, 1)),
+    ]);"crlf_indent")]
+    #[test_case("　◇　\n* * *"=>Document::new(vec![
+        Block::new_scene_break(Span::new("◇".into(), position(1, 0, 1))),
+        Block::new_scene_break(Span::new("* * *".into(), position(2, 10, 1))),
+    ]);"scene_break_with_space")]
+    #[test_case("＃＃ みだし\n#みだし\n####### みだし"=>Document::new(vec![
+        Block::new_heading(2, plaintext("みだし", 1, 7, 4), position(1, 0, 1)),
+        Block::new_paragraph(0, plaintext("#みだし", 2, 17, 1), position(2, 17, 1)),
+        Block::new_paragraph(0, TokenText::new(vec![
+            Token::new_plaintext(Span::new("#######".into(), position(3, 28, 1))),
+            Token::new_spase(Span::new(" ".into(), position(3, 35, 8))),
+            Token::new_plaintext(Span::new("みだし".into(), position(3, 36, 9))),
+        ]), position(3, 28, 1)),
+    ]);"heading")]
+    #[test_case(""=>Document::new(vec![]);"empty")]
+    fn document_from_works(input: &str) -> Document {
+        parse(input)
+    }
+
+    #[test_case("|漢字(かんじ)"=>position(1, 0, 1);"ruby")]
+    #[test_case("\n《《傍点》》"=>position(2, 1, 1);"emphasis_mark")]
+    #[test_case("\n\n|漢字$せつめい$"=>position(3, 2, 1);"annotation")]
+    fn block_position_works(input: &str) -> Position {
+        parse(input).last().unwrap().position().clone()
+    }
+
+    #[test_case("「あい」うえ"=>1.0 / 2.0;"paragraph")]
+    #[test_case("＊＊＊"=>0.0;"scene_break")]
+    fn block_dialogue_ratio_works(input: &str) -> f64 {
+        parse(input).last().unwrap().dialogue_ratio()
+    }
+}
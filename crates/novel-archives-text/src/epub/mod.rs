@@ -0,0 +1,424 @@
+use crate::glossary;
+use crate::html::{emphasis_mark_start, escape, render_token, EMPHASIS_CSS};
+use crate::parser::token::ParseContext;
+use crate::term::Term;
+use crate::{Id, Token, TokenText};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+mod zip;
+
+const TEXT_EMPHASIS_PREFIXES: [&str; 2] = ["-webkit-", "-epub-"];
+const MIMETYPE: &str = "application/epub+zip";
+const GLOSSARY_FILE: &str = "glossary.xhtml";
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml" />
+</rootfiles>
+</container>
+"#;
+
+const STYLE_CSS: &str = r#"html {
+  writing-mode: vertical-rl;
+  -webkit-writing-mode: vertical-rl;
+  -epub-writing-mode: vertical-rl;
+}
+.emphasis-mark {
+  font-style: normal;
+}
+a.term {
+  color: inherit;
+  text-decoration: none;
+}
+"#;
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct Chapter<'a> {
+    title: String,
+    text: TokenText<'a>,
+}
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct Book<'a> {
+    identifier: String,
+    title: String,
+    language: String,
+    modified: String,
+    chapters: Vec<Chapter<'a>>,
+    terms: Vec<Term>,
+}
+
+pub fn package(book: &Book) -> Vec<u8> {
+    let context = term_context(book);
+    let glossary = glossary::build(&context, book.chapters.iter().map(|chapter| &chapter.text));
+
+    let mut writer = zip::ZipWriter::default();
+    writer.add_file("mimetype", MIMETYPE.as_bytes());
+    writer.add_file("META-INF/container.xml", CONTAINER_XML.as_bytes());
+    writer.add_file(
+        "OEBPS/content.opf",
+        content_opf(book, !glossary.is_empty()).as_bytes(),
+    );
+    writer.add_file(
+        "OEBPS/nav.xhtml",
+        nav_xhtml(book, !glossary.is_empty()).as_bytes(),
+    );
+    writer.add_file("OEBPS/style.css", style_css().as_bytes());
+    for (i, chapter) in book.chapters.iter().enumerate() {
+        writer.add_file(
+            &format!("OEBPS/{}", chapter_file(i)),
+            chapter_xhtml(book, chapter).as_bytes(),
+        );
+    }
+    if !glossary.is_empty() {
+        writer.add_file(
+            &format!("OEBPS/{}", GLOSSARY_FILE),
+            glossary_xhtml(book, &glossary).as_bytes(),
+        );
+    }
+    writer.finish()
+}
+
+fn style_css() -> String {
+    let mut css = STYLE_CSS.to_string();
+    for (_, class, declaration) in EMPHASIS_CSS.iter() {
+        writeln!(css, ".emphasis-mark-{} {{", class).unwrap();
+        if declaration.starts_with("text-emphasis") {
+            for prefix in TEXT_EMPHASIS_PREFIXES.iter() {
+                writeln!(css, "  {}{};", prefix, declaration).unwrap();
+            }
+        }
+        writeln!(css, "  {};\n}}", declaration).unwrap();
+    }
+    css
+}
+
+fn chapter_file(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+fn content_opf(book: &Book, has_glossary: bool) -> String {
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    for i in 0..book.chapters.len() {
+        writeln!(
+            manifest,
+            "<item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\" />",
+            i + 1,
+            chapter_file(i)
+        )
+        .unwrap();
+        writeln!(spine, "<itemref idref=\"chapter-{}\" />", i + 1).unwrap();
+    }
+    if has_glossary {
+        writeln!(
+            manifest,
+            "<item id=\"glossary\" href=\"{}\" media-type=\"application/xhtml+xml\" />",
+            GLOSSARY_FILE
+        )
+        .unwrap();
+        spine.push_str("<itemref idref=\"glossary\" />\n");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{language}">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="book-id">{identifier}</dc:identifier>
+<dc:title>{title}</dc:title>
+<dc:language>{language}</dc:language>
+<meta property="dcterms:modified">{modified}</meta>
+<meta name="primary-writing-mode" content="vertical-rl" />
+</metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav" />
+<item id="style" href="style.css" media-type="text/css" />
+{manifest}</manifest>
+<spine page-progression-direction="rtl">
+{spine}</spine>
+</package>
+"#,
+        language = escape(&book.language),
+        identifier = escape(&book.identifier),
+        title = escape(&book.title),
+        modified = escape(&book.modified),
+        manifest = manifest,
+        spine = spine,
+    )
+}
+
+fn nav_xhtml(book: &Book, has_glossary: bool) -> String {
+    let mut toc = String::new();
+    for (i, chapter) in book.chapters.iter().enumerate() {
+        writeln!(
+            toc,
+            "<li><a href=\"{}\">{}</a></li>",
+            chapter_file(i),
+            escape(&chapter.title)
+        )
+        .unwrap();
+    }
+    if has_glossary {
+        writeln!(toc, "<li><a href=\"{}\">用語集</a></li>", GLOSSARY_FILE).unwrap();
+    }
+    xhtml(
+        book,
+        &book.title,
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n{}</ol>\n</nav>",
+            escape(&book.title),
+            toc
+        ),
+    )
+}
+
+fn chapter_xhtml(book: &Book, chapter: &Chapter) -> String {
+    let mut footnotes = vec![];
+    let body = render_text(&chapter.text, &mut footnotes);
+    xhtml(
+        book,
+        &chapter.title,
+        &format!(
+            "<section epub:type=\"chapter\">\n<h1>{}</h1>\n<div class=\"text\">{}</div>\n{}</section>",
+            escape(&chapter.title),
+            body,
+            footnotes_xhtml(&footnotes)
+        ),
+    )
+}
+
+fn footnotes_xhtml(footnotes: &[String]) -> String {
+    let mut notes = String::new();
+    for (index, footnote) in footnotes.iter().enumerate() {
+        writeln!(
+            notes,
+            "<aside epub:type=\"footnote\" id=\"{}\">{}</aside>",
+            note_id(index + 1),
+            footnote
+        )
+        .unwrap();
+    }
+    notes
+}
+
+fn note_id(number: usize) -> String {
+    format!("note-{}", number)
+}
+
+fn render_text(text: &TokenText, footnotes: &mut Vec<String>) -> String {
+    text.iter()
+        .map(|token| render_linked_token(token, footnotes))
+        .collect()
+}
+
+fn render_linked_token(token: &Token, footnotes: &mut Vec<String>) -> String {
+    match token {
+        Token::Term { body, term_id, .. } => format!(
+            "<a class=\"term\" href=\"{}#{}\">{}</a>",
+            GLOSSARY_FILE,
+            term_anchor(term_id),
+            escape(body.body())
+        ),
+        Token::Annotation {
+            body, description, ..
+        } => {
+            footnotes.push(String::new());
+            let number = footnotes.len();
+            let annotated = render_text(body, footnotes);
+            footnotes[number - 1] = render_text(description, footnotes);
+            format!(
+                "<span class=\"annotation-body\">{}</span><a epub:type=\"noteref\" href=\"#{}\">*</a>",
+                annotated,
+                note_id(number)
+            )
+        }
+        Token::EmphasisMark { body, style, .. } => format!(
+            "{}{}</em>",
+            emphasis_mark_start(*style),
+            render_text(body, footnotes)
+        ),
+        token => render_token(token),
+    }
+}
+
+fn glossary_xhtml(book: &Book, glossary: &[glossary::Entry]) -> String {
+    let mut entries = String::new();
+    let mut footnotes = vec![];
+    for entry in glossary {
+        writeln!(
+            entries,
+            "<dt id=\"{}\"><ruby>{}<rt>{}</rt></ruby></dt>\n<dd>{}</dd>",
+            term_anchor(entry.term_id()),
+            escape(entry.body()),
+            escape(entry.ruby()),
+            render_text(entry.description(), &mut footnotes)
+        )
+        .unwrap();
+    }
+    xhtml(
+        book,
+        "用語集",
+        &format!(
+            "<section epub:type=\"glossary\">\n<h1>用語集</h1>\n<dl>\n{}</dl>\n{}</section>",
+            entries,
+            footnotes_xhtml(&footnotes)
+        ),
+    )
+}
+
+fn xhtml(book: &Book, title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+<meta charset="UTF-8" />
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="style.css" />
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+        language = escape(&book.language),
+        title = escape(title),
+        body = body,
+    )
+}
+
+fn term_anchor(term_id: &Id<Term>) -> String {
+    format!("term-{}", escape(term_id.value()))
+}
+
+fn term_context(book: &Book) -> ParseContext {
+    ParseContext::new(Arc::new(
+        book.terms
+            .iter()
+            .map(|term| (term.body().clone(), term.clone()))
+            .collect::<BTreeMap<_, _>>(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::token::dialect;
+    use crate::parser::token::test_helper::{new_test_context, new_test_text};
+    use test_case::test_case;
+
+    fn terms() -> Vec<Term> {
+        vec![
+            Term::new(
+                Id::new("hozumi"),
+                "穂積".into(),
+                "ほづみ".into(),
+                "\"王都\"の司書(ししょ)。".into(),
+                false,
+            ),
+            Term::new(
+                Id::new("capital"),
+                "王都".into(),
+                "おうと".into(),
+                "".into(),
+                false,
+            ),
+            Term::new(
+                Id::new("unused"),
+                "未使用".into(),
+                "みしよう".into(),
+                "".into(),
+                false,
+            ),
+        ]
+    }
+
+    fn book<'a>(inputs: &[&'a str]) -> Book<'a> {
+        dialect_book(Arc::new(dialect::NovelArchives), inputs)
+    }
+
+    fn dialect_book<'a>(dialect: Arc<dyn dialect::Dialect>, inputs: &[&'a str]) -> Book<'a> {
+        let context = new_test_context(terms()).with_dialect(dialect);
+        Book::new(
+            "urn:uuid:0".into(),
+            "題名".into(),
+            "ja".into(),
+            "2021-01-01T00:00:00Z".into(),
+            inputs
+                .iter()
+                .enumerate()
+                .map(|(i, input)| {
+                    Chapter::new(format!("第{}話", i + 1), new_test_text(&context, input))
+                })
+                .collect(),
+            terms(),
+        )
+    }
+
+    #[test_case(Arc::new(dialect::NovelArchives), "|漢字$せつめい$と|本《ほん》"=>"<section epub:type=\"chapter\">\n<h1>第1話</h1>\n<div class=\"text\"><span class=\"annotation-body\">漢字</span><a epub:type=\"noteref\" href=\"#note-1\">*</a>と<ruby><rb>本</rb><rp>(</rp><rt>ほん</rt><rp>)</rp></ruby></div>\n<aside epub:type=\"footnote\" id=\"note-1\">せつめい</aside>\n</section>";"annotation")]
+    #[test_case(Arc::new(dialect::NovelArchives), "\"穂積\""=>"<section epub:type=\"chapter\">\n<h1>第1話</h1>\n<div class=\"text\"><a class=\"term\" href=\"glossary.xhtml#term-hozumi\">穂積</a></div>\n</section>";"term")]
+    #[test_case(Arc::new(dialect::Aozora), "［＃傍点］※［＃米印、1-2-8］［＃傍点終わり］"=>"<section epub:type=\"chapter\">\n<h1>第1話</h1>\n<div class=\"text\"><em class=\"emphasis-mark emphasis-mark-sesame\" style=\"text-emphasis-style: sesame\"><span class=\"annotation-body\">※</span><a epub:type=\"noteref\" href=\"#note-1\">*</a></em></div>\n<aside epub:type=\"footnote\" id=\"note-1\">米印、1-2-8</aside>\n</section>";"nested_annotation")]
+    fn chapter_xhtml_works(dialect: Arc<dyn dialect::Dialect>, input: &str) -> String {
+        let book = dialect_book(dialect, &[input]);
+        let xhtml = chapter_xhtml(&book, &book.chapters[0]);
+        let start = xhtml.find("<section").unwrap();
+        let end = xhtml.find("</section>").unwrap() + "</section>".len();
+        xhtml[start..end].to_string()
+    }
+
+    #[test_case(&["本文"]=>Vec::<String>::new();"no_terms")]
+    #[test_case(&["\"穂積\"", "|\"穂積\"$\"穂積\"$"]=>vec!["capital".to_string(), "hozumi".to_string()];"dedup")]
+    fn glossary_works(inputs: &[&str]) -> Vec<String> {
+        let book = book(inputs);
+        let context = term_context(&book);
+        glossary::build(&context, book.chapters.iter().map(|chapter| &chapter.text))
+            .into_iter()
+            .map(|entry| entry.term_id().value().clone())
+            .collect()
+    }
+
+    #[test]
+    fn glossary_xhtml_works() {
+        let book = book(&["\"穂積\""]);
+        let context = term_context(&book);
+        let glossary = glossary::build(&context, book.chapters.iter().map(|chapter| &chapter.text));
+        let xhtml = glossary_xhtml(&book, &glossary);
+        let start = xhtml.find("<dl>").unwrap();
+        let end = xhtml.find("</dl>").unwrap() + "</dl>".len();
+        assert_eq!(
+            &xhtml[start..end],
+            "<dl>\n<dt id=\"term-capital\"><ruby>王都<rt>おうと</rt></ruby></dt>\n<dd></dd>\n<dt id=\"term-hozumi\"><ruby>穂積<rt>ほづみ</rt></ruby></dt>\n<dd><a class=\"term\" href=\"glossary.xhtml#term-capital\">王都</a>の<ruby><rb>司書</rb><rp>(</rp><rt>ししょ</rt><rp>)</rp></ruby>。</dd>\n</dl>"
+        );
+    }
+
+    #[test]
+    fn style_css_works() {
+        let css = style_css();
+        assert!(css.contains(
+            ".emphasis-mark-open-sesame {\n  -webkit-text-emphasis-style: open sesame;\n  -epub-text-emphasis-style: open sesame;\n  text-emphasis-style: open sesame;\n}\n"
+        ));
+        assert!(css.contains(".emphasis-mark-side-line {\n  text-decoration: underline;\n}\n"));
+        assert!(!css.contains(".emphasis-mark {\n  font-style: normal;\n  -webkit"));
+    }
+
+    #[test]
+    fn package_works() {
+        let epub = package(&book(&["\"穂積\"は"]));
+        assert_eq!(&epub[30..38], b"mimetype");
+        assert_eq!(&epub[38..58], MIMETYPE.as_bytes());
+        let contains = |needle: &str| {
+            epub.windows(needle.len())
+                .any(|window| window == needle.as_bytes())
+        };
+        assert!(contains("OEBPS/content.opf"));
+        assert!(contains("OEBPS/chapter-1.xhtml"));
+        assert!(contains("OEBPS/glossary.xhtml"));
+        assert!(contains(
+            "<dt id=\"term-hozumi\"><ruby>穂積<rt>ほづみ</rt></ruby></dt>"
+        ));
+        assert!(!contains("未使用"));
+    }
+}
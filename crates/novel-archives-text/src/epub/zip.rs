@@ -0,0 +1,120 @@
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const VERSION: u16 = 20;
+const UTF8_FLAG: u16 = 0x0800;
+const STORED: u16 = 0;
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+#[derive(Debug, Default)]
+pub struct ZipWriter {
+    buffer: Vec<u8>,
+    central_directory: Vec<u8>,
+    entry_count: u16,
+}
+
+impl ZipWriter {
+    pub fn add_file(&mut self, name: &str, data: &[u8]) {
+        let offset = self.buffer.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        push_u32(&mut self.buffer, LOCAL_FILE_HEADER_SIGNATURE);
+        push_entry_header(&mut self.buffer, name, crc, size);
+        self.buffer.extend_from_slice(name.as_bytes());
+        self.buffer.extend_from_slice(data);
+
+        push_u32(
+            &mut self.central_directory,
+            CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+        );
+        push_u16(&mut self.central_directory, VERSION);
+        push_entry_header(&mut self.central_directory, name, crc, size);
+        push_u16(&mut self.central_directory, 0);
+        push_u16(&mut self.central_directory, 0);
+        push_u16(&mut self.central_directory, 0);
+        push_u32(&mut self.central_directory, 0);
+        push_u32(&mut self.central_directory, offset);
+        self.central_directory.extend_from_slice(name.as_bytes());
+
+        self.entry_count += 1;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let offset = self.buffer.len() as u32;
+        let size = self.central_directory.len() as u32;
+        self.buffer.append(&mut self.central_directory);
+
+        push_u32(&mut self.buffer, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        push_u16(&mut self.buffer, 0);
+        push_u16(&mut self.buffer, 0);
+        push_u16(&mut self.buffer, self.entry_count);
+        push_u16(&mut self.buffer, self.entry_count);
+        push_u32(&mut self.buffer, size);
+        push_u32(&mut self.buffer, offset);
+        push_u16(&mut self.buffer, 0);
+        self.buffer
+    }
+}
+
+fn push_entry_header(buffer: &mut Vec<u8>, name: &str, crc: u32, size: u32) {
+    push_u16(buffer, VERSION);
+    push_u16(buffer, UTF8_FLAG);
+    push_u16(buffer, STORED);
+    push_u16(buffer, DOS_TIME);
+    push_u16(buffer, DOS_DATE);
+    push_u32(buffer, crc);
+    push_u32(buffer, size);
+    push_u32(buffer, size);
+    push_u16(buffer, name.len() as u16);
+    push_u16(buffer, 0);
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(b"" => 0)]
+    #[test_case(b"123456789" => 0xcbf4_3926)]
+    #[test_case(b"application/epub+zip" => 0x2cab_616f)]
+    fn crc32_works(data: &[u8]) -> u32 {
+        crc32(data)
+    }
+
+    #[test]
+    fn zip_writer_works() {
+        let mut writer = ZipWriter::default();
+        writer.add_file("mimetype", b"application/epub+zip");
+        let zip = writer.finish();
+        assert_eq!(&zip[0..4], b"PK\x03\x04");
+        assert_eq!(&zip[30..38], b"mimetype");
+        assert_eq!(&zip[38..58], b"application/epub+zip");
+        assert_eq!(&zip[58..62], b"PK\x01\x02");
+        assert_eq!(&zip[zip.len() - 22..zip.len() - 18], b"PK\x05\x06");
+    }
+}
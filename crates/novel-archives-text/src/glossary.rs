@@ -0,0 +1,188 @@
+use super::*;
+use parser::token::ParseContext;
+use std::collections::BTreeMap;
+use term::Term;
+
+const VOICED_KANA: &str = "がぎぐげござじずぜぞだぢづでどばびぶべぼぱぴぷぺぽゔ";
+const UNVOICED_KANA: &str = "かきくけこさしすせそたちつてとはひふへほはひふへほう";
+const SMALL_KANA: &str = "ぁぃぅぇぉっゃゅょゎゕゖ";
+const LARGE_KANA: &str = "あいうえおつやゆよわかけ";
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct Entry<'a> {
+    term_id: Id<Term>,
+    body: String,
+    ruby: String,
+    description: TokenText<'a>,
+    has_detail: bool,
+}
+
+pub fn build<'a, 'b, 't: 'b>(
+    context: &'a ParseContext,
+    texts: impl IntoIterator<Item = &'b TokenText<'t>>,
+) -> Vec<Entry<'a>> {
+    let terms: BTreeMap<_, _> = context
+        .terms()
+        .map(|term| (term.id().value(), term))
+        .collect();
+    let mut ids = vec![];
+    for text in texts {
+        collect_term_ids(text, &mut ids);
+    }
+    let mut entries = vec![];
+    let mut index = 0;
+    while index < ids.len() {
+        if let Some(term) = terms.get(ids[index].value()) {
+            let description = term.parse_description(context);
+            collect_term_ids(&description, &mut ids);
+            entries.push(Entry::new(
+                term.id().clone(),
+                term.body().clone(),
+                term.ruby().clone(),
+                description,
+                *term.has_detail(),
+            ));
+        }
+        index += 1;
+    }
+    entries.sort_by_cached_key(|entry| {
+        let reading = if entry.ruby.is_empty() {
+            &entry.body
+        } else {
+            &entry.ruby
+        };
+        (reading_key(reading), entry.body.clone())
+    });
+    entries
+}
+
+fn reading_key(reading: &str) -> (Vec<char>, Vec<char>) {
+    let kana: Vec<char> = term::normalize(reading).chars().map(to_hiragana).collect();
+    (kana.iter().map(|&c| to_seion(c)).collect(), kana)
+}
+
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => std::char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn to_seion(c: char) -> char {
+    [(VOICED_KANA, UNVOICED_KANA), (SMALL_KANA, LARGE_KANA)]
+        .iter()
+        .find_map(|(from, to)| {
+            from.chars()
+                .position(|from| from == c)
+                .and_then(|index| to.chars().nth(index))
+        })
+        .unwrap_or(c)
+}
+
+fn collect_term_ids(text: &TokenText, ids: &mut Vec<Id<Term>>) {
+    for token in text.iter() {
+        match token {
+            Token::Term { term_id, .. } if !ids.contains(term_id) => ids.push(term_id.clone()),
+            Token::Annotation {
+                body, description, ..
+            } => {
+                collect_term_ids(body, ids);
+                collect_term_ids(description, ids);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::token::test_helper::{new_test_context, new_test_text};
+    use test_case::test_case;
+
+    fn context() -> ParseContext {
+        let terms = vec![
+            Term::new(
+                Id::new("hozumi"),
+                "穂積".into(),
+                "ほづみ".into(),
+                "\"王立図書館\"の司書。".into(),
+                false,
+            ),
+            Term::new(
+                Id::new("library"),
+                "王立図書館".into(),
+                "おうりつとしょかん".into(),
+                "|古(ふる)い図書館。".into(),
+                true,
+            ),
+            Term::new(
+                Id::new("katsuragi"),
+                "葛城".into(),
+                "カツラギ".into(),
+                "".into(),
+                false,
+            ),
+            Term::new(
+                Id::new("grimoire"),
+                "魔導書".into(),
+                "".into(),
+                "".into(),
+                false,
+            ),
+        ];
+        new_test_context(terms)
+    }
+
+    #[test_case(&["\"穂積\"と\"葛城\""]=>vec!["library", "katsuragi", "hozumi"];"gojuon_order")]
+    #[test_case(&["\"葛城\"", "|注$\"穂積\"$"]=>vec!["library", "katsuragi", "hozumi"];"across_texts")]
+    #[test_case(&["\"魔導書\"と\"葛城\""]=>vec!["katsuragi", "grimoire"];"without_ruby")]
+    #[test_case(&["本文"]=>Vec::<String>::new();"no_terms")]
+    fn build_works(inputs: &[&str]) -> Vec<String> {
+        let context = context();
+        let texts: Vec<_> = inputs
+            .iter()
+            .map(|input| new_test_text(&context, input))
+            .collect();
+        build(&context, texts.iter())
+            .into_iter()
+            .map(|entry| entry.term_id().value().clone())
+            .collect()
+    }
+
+    #[test]
+    fn build_parses_description() {
+        let context = context();
+        let text = new_test_text(&context, "\"穂積\"");
+        let entries = build(&context, std::iter::once(&text));
+        let descriptions: Vec<_> = entries
+            .iter()
+            .map(|entry| {
+                let mut ids = vec![];
+                collect_term_ids(entry.description(), &mut ids);
+                (
+                    entry.term_id().value().clone(),
+                    entry.description().len(),
+                    ids,
+                )
+            })
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                ("library".to_string(), 2, vec![]),
+                ("hozumi".to_string(), 2, vec![Id::new("library")]),
+            ]
+        );
+    }
+
+    #[test_case(&["ほづみ", "おうりつとしょかん", "カツラギ", "かっこ"]=>vec!["おうりつとしょかん", "かっこ", "カツラギ", "ほづみ"];"gojuon")]
+    #[test_case(&["ぱぱ", "ばば", "はば", "はは"]=>vec!["はは", "はば", "ばば", "ぱぱ"];"voicing")]
+    #[test_case(&["ｶｷ", "かぎ", "かく"]=>vec!["ｶｷ", "かぎ", "かく"];"half_width")]
+    fn reading_key_works(readings: &[&str]) -> Vec<String> {
+        let mut readings: Vec<String> =
+            readings.iter().map(|reading| reading.to_string()).collect();
+        readings.sort_by_key(|reading| reading_key(reading));
+        readings
+    }
+}
@@ -0,0 +1,285 @@
+use crate::dialogue::{self, Segment};
+use crate::document::{Block, Document};
+use crate::{EmphasisStyle, Token, TokenText};
+use std::fmt::Write;
+
+pub fn render(text: &TokenText) -> String {
+    let mut html = String::new();
+    write_text(&mut html, text);
+    html
+}
+
+pub fn render_token(token: &Token) -> String {
+    let mut html = String::new();
+    write_token(&mut html, token);
+    html
+}
+
+pub fn render_document(document: &Document) -> String {
+    let mut html = String::new();
+    for block in document.iter() {
+        write_block(&mut html, block);
+    }
+    html
+}
+
+pub fn escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_text(html: &mut String, text: &TokenText) {
+    for token in text.iter() {
+        write_token(html, token);
+    }
+}
+
+fn write_block(html: &mut String, block: &Block) {
+    match block {
+        Block::Paragraph { indent, body, .. } => {
+            if *indent > 0 {
+                write!(html, "<p style=\"text-indent: {}em\">", indent).unwrap();
+            } else {
+                html.push_str("<p>");
+            }
+            write_segments(html, &dialogue::split(body).0);
+            html.push_str("</p>\n");
+        }
+        Block::Heading { level, body, .. } => {
+            write!(html, "<h{}>", level).unwrap();
+            write_text(html, body);
+            writeln!(html, "</h{}>", level).unwrap();
+        }
+        Block::SceneBreak(mark) => {
+            writeln!(
+                html,
+                "<hr class=\"scene-break\" data-mark=\"{}\" />",
+                escape(mark.body())
+            )
+            .unwrap();
+        }
+        Block::BlankLine { count, .. } => {
+            for _ in 0..*count {
+                html.push_str("<p class=\"blank-line\"><br /></p>\n");
+            }
+        }
+    }
+}
+
+// The class suffix and the declaration of each style, shared with the EPUB stylesheet.
+pub(crate) const EMPHASIS_CSS: [(EmphasisStyle, &str, &str); 8] = [
+    (
+        EmphasisStyle::Sesame,
+        "sesame",
+        "text-emphasis-style: sesame",
+    ),
+    (
+        EmphasisStyle::OpenSesame,
+        "open-sesame",
+        "text-emphasis-style: open sesame",
+    ),
+    (
+        EmphasisStyle::Dot,
+        "dot",
+        "text-emphasis-style: filled circle",
+    ),
+    (
+        EmphasisStyle::Circle,
+        "circle",
+        "text-emphasis-style: open circle",
+    ),
+    (
+        EmphasisStyle::DoubleCircle,
+        "double-circle",
+        "text-emphasis-style: double-circle",
+    ),
+    (
+        EmphasisStyle::Triangle,
+        "triangle",
+        "text-emphasis-style: triangle",
+    ),
+    (
+        EmphasisStyle::OpenTriangle,
+        "open-triangle",
+        "text-emphasis-style: open triangle",
+    ),
+    (
+        EmphasisStyle::SideLine,
+        "side-line",
+        "text-decoration: underline",
+    ),
+];
+
+pub(crate) fn emphasis_mark_start(style: EmphasisStyle) -> String {
+    let (class, declaration) = emphasis_css(style);
+    format!(
+        "<em class=\"emphasis-mark emphasis-mark-{}\" style=\"{}\">",
+        class, declaration
+    )
+}
+
+fn emphasis_css(style: EmphasisStyle) -> (&'static str, &'static str) {
+    EMPHASIS_CSS
+        .iter()
+        .find(|(emphasis_style, _, _)| *emphasis_style == style)
+        .map(|(_, class, declaration)| (*class, *declaration))
+        .unwrap()
+}
+
+fn write_segments(html: &mut String, segments: &[Segment]) {
+    for segment in segments {
+        match segment {
+            Segment::Narration(text) => write_text(html, text),
+            Segment::Dialogue {
+                body, delimiter, ..
+            } => {
+                html.push_str("<span class=\"dialogue\">");
+                html.push_str(&escape(delimiter.start()));
+                write_segments(html, body);
+                html.push_str(&escape(delimiter.end()));
+                html.push_str("</span>");
+            }
+        }
+    }
+}
+
+fn write_token(html: &mut String, token: &Token) {
+    match token {
+        Token::Term { body, term_id, .. } => {
+            let term_id = escape(term_id.value());
+            write!(
+                html,
+                "<a class=\"term\" href=\"#term-{}\" data-term-id=\"{}\">{}</a>",
+                term_id,
+                term_id,
+                escape(body.body())
+            )
+            .unwrap();
+        }
+        Token::Ruby { body, ruby, .. } | Token::KanjiRuby { body, ruby, .. } => {
+            write!(
+                html,
+                "<ruby><rb>{}</rb><rp>(</rp><rt>{}</rt><rp>)</rp></ruby>",
+                escape(body.body()),
+                escape(ruby.body())
+            )
+            .unwrap();
+        }
+        Token::MonoRuby { pairs, .. } => {
+            html.push_str("<ruby class=\"mono-ruby\">");
+            for pair in pairs {
+                write!(
+                    html,
+                    "<rb>{}</rb><rp>(</rp><rt>{}</rt><rp>)</rp>",
+                    escape(pair.body().body()),
+                    escape(pair.ruby().body())
+                )
+                .unwrap();
+            }
+            html.push_str("</ruby>");
+        }
+        Token::Annotation {
+            body, description, ..
+        } => {
+            html.push_str("<span class=\"annotation\"><span class=\"annotation-body\">");
+            write_text(html, body);
+            html.push_str("</span><span class=\"annotation-description\" role=\"note\">");
+            write_text(html, description);
+            html.push_str("</span></span>");
+        }
+        Token::EmphasisMark { body, style, .. } => {
+            html.push_str(&emphasis_mark_start(*style));
+            write_text(html, body);
+            html.push_str("</em>");
+        }
+        Token::Escape { body, .. } | Token::Spase(body) | Token::Plaintext(body) => {
+            html.push_str(&escape(body.body()))
+        }
+        Token::Ignore(_) => {}
+        Token::NewLine(_) => html.push_str("<br />\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Delimiter, Id, Position, RubyPair, Span};
+    use test_case::test_case;
+
+    fn span(body: &str) -> Span {
+        Span::new(body.into(), Position::default())
+    }
+
+    fn delimiter(start: &'static str, end: &'static str) -> Delimiter<'static> {
+        Delimiter::new(start.into(), end.into())
+    }
+
+    #[test_case(Token::new_plaintext(span("<a & 'b'>"))=>"&lt;a &amp; &#39;b&#39;&gt;";"plaintext_escape")]
+    #[test_case(Token::new_ruby("|".into(),span("漢字"),span("かんじ"),delimiter("《","》"))=>"<ruby><rb>漢字</rb><rp>(</rp><rt>かんじ</rt><rp>)</rp></ruby>";"ruby")]
+    #[test_case(Token::new_kanji_ruby(span("漢字"),span("<b>"),delimiter("(",")"))=>"<ruby><rb>漢字</rb><rp>(</rp><rt>&lt;b&gt;</rt><rp>)</rp></ruby>";"kanji_ruby_escape")]
+    #[test_case(Token::new_mono_ruby(span("漢字"),span("かん・じ"),vec![RubyPair::new(span("漢"),span("かん")),RubyPair::new(span("字"),span("じ"))],delimiter("(",")"))
+        =>"<ruby class=\"mono-ruby\"><rb>漢</rb><rp>(</rp><rt>かん</rt><rp>)</rp><rb>字</rb><rp>(</rp><rt>じ</rt><rp>)</rp></ruby>";"mono_ruby")]
+    #[test_case(Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(span("傍点"))]),EmphasisStyle::Sesame,delimiter("《《","》》"))=>"<em class=\"emphasis-mark emphasis-mark-sesame\" style=\"text-emphasis-style: sesame\">傍点</em>";"emphasis_mark")]
+    #[test_case(Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(span("傍点"))]),EmphasisStyle::OpenSesame,delimiter("《《白ゴマ|","》》"))=>"<em class=\"emphasis-mark emphasis-mark-open-sesame\" style=\"text-emphasis-style: open sesame\">傍点</em>";"emphasis_mark_style")]
+    #[test_case(Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(span("傍点"))]),EmphasisStyle::Dot,delimiter("《《丸|","》》"))=>"<em class=\"emphasis-mark emphasis-mark-dot\" style=\"text-emphasis-style: filled circle\">傍点</em>";"emphasis_mark_dot")]
+    #[test_case(Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(span("a")),Token::new_escape("|".into(),span("》"))]),EmphasisStyle::Sesame,delimiter("《《","》》"))=>"<em class=\"emphasis-mark emphasis-mark-sesame\" style=\"text-emphasis-style: sesame\">a》</em>";"emphasis_mark_escape")]
+    #[test_case(Token::new_term(span("穂積"),Id::new("term\"1"),delimiter("\"","\""))=>"<a class=\"term\" href=\"#term-term&quot;1\" data-term-id=\"term&quot;1\">穂積</a>";"term")]
+    #[test_case(Token::new_annotation("|".into(),TokenText::new(vec![Token::new_plaintext(span("漢字"))]),TokenText::new(vec![Token::new_kanji_ruby(span("説明"),span("せつめい"),delimiter("(",")"))]),delimiter("$","$"))
+        =>"<span class=\"annotation\"><span class=\"annotation-body\">漢字</span><span class=\"annotation-description\" role=\"note\"><ruby><rb>説明</rb><rp>(</rp><rt>せつめい</rt><rp>)</rp></ruby></span></span>";"annotation")]
+    #[test_case(Token::new_ignore(span("|"))=>"";"ignore")]
+    #[test_case(Token::new_new_line(span("\r\n"))=>"<br />\n";"new_line")]
+    #[test_case(Token::new_spase(span("　"))=>"　";"space")]
+    fn render_works(token: Token) -> String {
+        render(&TokenText::new(vec![token]))
+    }
+
+    #[test]
+    fn render_document_works() {
+        let document = Document::new(vec![
+            Block::new_heading(
+                1,
+                TokenText::new(vec![Token::new_plaintext(span("章"))]),
+                Position::default(),
+            ),
+            Block::new_paragraph(
+                1,
+                TokenText::new(vec![Token::new_plaintext(span("本文"))]),
+                Position::default(),
+            ),
+            Block::new_blank_line(2, Position::default()),
+            Block::new_scene_break(span("<◇>")),
+            Block::new_paragraph(
+                0,
+                TokenText::new(vec![Token::new_plaintext(span("続き"))]),
+                Position::default(),
+            ),
+        ]);
+        assert_eq!(
+            render_document(&document),
+            "<h1>章</h1>\n<p style=\"text-indent: 1em\">本文</p>\n<p class=\"blank-line\"><br /></p>\n<p class=\"blank-line\"><br /></p>\n<hr class=\"scene-break\" data-mark=\"&lt;◇&gt;\" />\n<p>続き</p>\n"
+        );
+    }
+
+    #[test]
+    fn render_document_dialogue_works() {
+        let document = Document::new(vec![Block::new_paragraph(
+            0,
+            TokenText::new(vec![Token::new_plaintext(span("あ「い『う』」"))]),
+            Position::default(),
+        )]);
+        assert_eq!(
+            render_document(&document),
+            "<p>あ<span class=\"dialogue\">「い<span class=\"dialogue\">『う』</span>」</span></p>\n"
+        );
+    }
+}
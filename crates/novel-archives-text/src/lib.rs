@@ -4,10 +4,20 @@ extern crate derive_new;
 #[macro_use]
 extern crate derive_getters;
 
+pub mod aozora;
+pub mod concordance;
+pub mod counting;
+pub mod dialogue;
+pub mod document;
+pub mod epub;
+pub mod glossary;
+pub mod html;
 mod id;
 pub mod parser;
+pub mod serializer;
 pub mod term;
 mod token;
+pub mod validation;
 
 pub use id::*;
 pub use token::*;
@@ -0,0 +1,279 @@
+use super::*;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum DiagnosticKind {
+    RubyTooLong,
+    UnclosedDelimiter,
+    UnknownTerm,
+    EmptyRubyBody,
+    AnnotationWithoutDescription,
+    UnbalancedDialogue,
+    RubyContainsKanji,
+    RubyMixedScripts,
+    RubyImplausibleLength,
+    CyclicTermReference,
+    UnresolvedReference,
+}
+
+impl DiagnosticKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::UnclosedDelimiter => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+
+    fn message(&self, body: &str) -> String {
+        match self {
+            DiagnosticKind::RubyTooLong => format!("ruby \"{}\" is too long for its body", body),
+            DiagnosticKind::UnclosedDelimiter => format!("\"{}\" is never closed", body),
+            DiagnosticKind::UnknownTerm => format!("\"{}\" is not in the term map", body),
+            DiagnosticKind::EmptyRubyBody => format!("ruby after \"{}\" has no body", body),
+            DiagnosticKind::AnnotationWithoutDescription => {
+                format!("annotation \"{}\" has no description", body)
+            }
+            DiagnosticKind::UnbalancedDialogue => format!("\"{}\" has no matching bracket", body),
+            DiagnosticKind::RubyContainsKanji => format!("ruby \"{}\" contains kanji", body),
+            DiagnosticKind::RubyMixedScripts => format!("ruby \"{}\" mixes scripts", body),
+            DiagnosticKind::RubyImplausibleLength => {
+                format!(
+                    "ruby \"{}\" is implausibly long or short for its body",
+                    body
+                )
+            }
+            DiagnosticKind::CyclicTermReference => {
+                format!("reference to \"{}\" forms a cycle", body)
+            }
+            DiagnosticKind::UnresolvedReference => {
+                format!("\"{}\" refers to text that does not precede it", body)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct Diagnostic {
+    severity: Severity,
+    kind: DiagnosticKind,
+    span: Span<'static>,
+    message: String,
+}
+
+impl Diagnostic {
+    pub fn from_kind(kind: DiagnosticKind, span: token::ParsedSpan) -> Self {
+        Self::from_span(kind, span.into())
+    }
+
+    pub fn from_span(kind: DiagnosticKind, span: Span) -> Self {
+        let message = kind.message(span.body());
+        Self::new(kind.severity(), kind, span.into_owned(), message)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics(Arc<Mutex<Reported>>);
+
+#[derive(Debug, Default)]
+struct Reported {
+    keys: HashSet<(DiagnosticKind, Position)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn report(&self, diagnostic: Diagnostic) {
+        let mut reported = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (diagnostic.kind, diagnostic.span.original_position().clone());
+        if reported.keys.insert(key) {
+            reported.diagnostics.push(diagnostic);
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .diagnostics
+            .clone();
+        diagnostics.sort_by_key(|diagnostic| *diagnostic.span.original_position().byte_offset());
+        diagnostics
+    }
+}
+
+pub fn parse_with_diagnostics(
+    context: token::ParseContext,
+    input: &str,
+) -> (TokenText<'_>, Vec<Diagnostic>) {
+    let context = context.with_diagnostics();
+    let text = token::iterator::TextIterator::new(context.clone(), token::ParsedSpan::new(input))
+        .collect();
+    (text, context.diagnostics())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use test_case::test_case;
+
+    fn diagnostic(
+        kind: DiagnosticKind,
+        body: &str,
+        offset: usize,
+    ) -> (DiagnosticKind, String, usize) {
+        (kind, body.into(), offset)
+    }
+
+    #[test_case("本文です"=>Vec::<(DiagnosticKind, String, usize)>::new();"none")]
+    #[test_case("|漢字(かんじ)と\"無\""=>Vec::<(DiagnosticKind, String, usize)>::new();"valid")]
+    #[test_case("|漢字(あいうえおかきくけこさしすせそたちつてとなにぬねの)"=>vec![
+        diagnostic(DiagnosticKind::RubyTooLong, "あいうえおかきくけこさしすせそたちつてとなにぬねの", 8),
+    ];"directive_ruby_too_long")]
+    #[test_case("漢(あいうえおかきくけこさ)"=>vec![
+        diagnostic(DiagnosticKind::RubyTooLong, "あいうえおかきくけこさ", 4),
+    ];"kanji_ruby_too_long")]
+    #[test_case("漢字《かんじ"=>vec![
+        diagnostic(DiagnosticKind::UnclosedDelimiter, "《", 6),
+    ];"unclosed_kanji_ruby")]
+    #[test_case("|ほげ《ふが\n"=>vec![
+        diagnostic(DiagnosticKind::UnclosedDelimiter, "《", 7),
+    ];"unclosed_directive_ruby")]
+    #[test_case("《《傍点"=>vec![
+        diagnostic(DiagnosticKind::UnclosedDelimiter, "《《", 0),
+    ];"unclosed_emphasis_mark")]
+    #[test_case("\"無"=>vec![
+        diagnostic(DiagnosticKind::UnclosedDelimiter, "\"", 0),
+    ];"unclosed_term")]
+    #[test_case("|漢字$せつめい"=>vec![
+        diagnostic(DiagnosticKind::UnclosedDelimiter, "$", 7),
+    ];"unclosed_annotation")]
+    #[test_case("\"有\"と\"無\""=>vec![
+        diagnostic(DiagnosticKind::UnknownTerm, "有", 1),
+    ];"unknown_term")]
+    #[test_case("|(かんじ)"=>Vec::<(DiagnosticKind, String, usize)>::new();"escaped_ruby_start")]
+    #[test_case("|漢字$$"=>vec![
+        diagnostic(DiagnosticKind::AnnotationWithoutDescription, "漢字", 1),
+    ];"annotation_without_description")]
+    #[test_case("|漢字$\"有\"$"=>vec![
+        diagnostic(DiagnosticKind::UnknownTerm, "有", 9),
+    ];"in_annotation")]
+    fn parse_with_diagnostics_works(input: &str) -> Vec<(DiagnosticKind, String, usize)> {
+        let terms = vec![term::Term::new(
+            Id::new("term_id1"),
+            "無".into(),
+            "む".into(),
+            "".into(),
+            false,
+        )];
+        let context = token::test_helper::new_test_context(terms);
+        let (_, diagnostics) = parse_with_diagnostics(context, input);
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.kind,
+                    diagnostic.span.body().to_string(),
+                    *diagnostic.span.original_position().byte_offset(),
+                )
+            })
+            .collect()
+    }
+
+    #[test_case("［＃「ない」に傍点］"=>vec![
+        diagnostic(DiagnosticKind::UnresolvedReference, "［＃「ない」に傍点］", 0),
+    ];"unresolved_emphasis_mark")]
+    #[test_case("本文［＃「ない」は縦中横］"=>vec![
+        diagnostic(DiagnosticKind::UnresolvedReference, "「ない」は縦中横", 12),
+    ];"unresolved_annotation")]
+    #[test_case("本文［＃「本文」に傍点］"=>Vec::<(DiagnosticKind, String, usize)>::new();"resolved")]
+    fn parse_aozora_with_diagnostics_works(input: &str) -> Vec<(DiagnosticKind, String, usize)> {
+        let context = token::ParseContext::new(Arc::new(BTreeMap::new()))
+            .with_dialect(Arc::new(token::dialect::Aozora));
+        let (_, diagnostics) = parse_with_diagnostics(context, input);
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.kind,
+                    diagnostic.span.body().to_string(),
+                    *diagnostic.span.original_position().byte_offset(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diagnostic_works() {
+        let diagnostic =
+            Diagnostic::from_kind(DiagnosticKind::UnknownTerm, token::ParsedSpan::new("有"));
+        assert_eq!(diagnostic.severity(), &Severity::Warning);
+        assert_eq!(diagnostic.message(), "\"有\" is not in the term map");
+        assert_eq!(
+            diagnostic.span().original_position(),
+            &Position::new(1, 0).with_columns(1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn diagnostics_report_works() {
+        let diagnostics = Diagnostics::default();
+        let poisoned = diagnostics.clone();
+        let _ = std::thread::spawn(move || {
+            let _reported = poisoned.0.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+        for input in ["有", "有"] {
+            diagnostics.report(Diagnostic::from_kind(
+                DiagnosticKind::UnknownTerm,
+                token::ParsedSpan::new(input),
+            ));
+        }
+        diagnostics.report(Diagnostic::from_kind(
+            DiagnosticKind::UnclosedDelimiter,
+            token::ParsedSpan::new("有"),
+        ));
+        assert_eq!(
+            diagnostics
+                .to_vec()
+                .iter()
+                .map(|diagnostic| (*diagnostic.kind(), diagnostic.span().body().to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                (DiagnosticKind::UnknownTerm, "有".to_string()),
+                (DiagnosticKind::UnclosedDelimiter, "有".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_ruby_body_works() {
+        let context = token::ParseContext::default()
+            .with_options(token::ParserOptions::default().with_escape(false));
+        let (_, diagnostics) = parse_with_diagnostics(context, "|(かんじ)");
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::from_kind(
+                DiagnosticKind::EmptyRubyBody,
+                token::ParsedSpan::new("|")
+            )]
+        );
+    }
+
+    #[test]
+    fn diagnostics_disabled_works() {
+        let context = token::ParseContext::default();
+        let _: TokenText =
+            token::iterator::TextIterator::new(context.clone(), token::ParsedSpan::new("\"有\""))
+                .collect();
+        assert!(context.diagnostics().is_empty());
+    }
+}
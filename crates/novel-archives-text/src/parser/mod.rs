@@ -1,8 +1,10 @@
+mod diagnostic;
 mod error;
 pub mod nom_extend;
 mod result;
 pub mod token;
 
+pub use diagnostic::*;
 pub use error::*;
 pub use result::*;
 
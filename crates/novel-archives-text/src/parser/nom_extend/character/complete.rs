@@ -106,6 +106,48 @@ pub fn end_emphasis_mark(input: token::ParsedSpan) -> NomIResult {
     Ok(input.take_split(parsed1.fragment().len() + parsed2.fragment().len()))
 }
 
+pub fn start_aozora_note(input: token::ParsedSpan) -> NomIResult {
+    let (input1, parsed1) = take_while_m_n(1, 1, character::is_start_aozora_note)(input)?;
+    let (_, parsed2) = take_while_m_n(1, 1, character::is_aozora_note_mark)(input1)?;
+    Ok(input.take_split(parsed1.fragment().len() + parsed2.fragment().len()))
+}
+
+pub fn able_to_aozora_note(input: token::ParsedSpan) -> NomIResult {
+    let fragment = input.fragment();
+    let mut len = 0;
+    while let Some(c) = fragment[len..].chars().next() {
+        if let Some(nested_len) = nested_gaiji_len(&fragment[len..]) {
+            len += nested_len;
+        } else if complete::is_able_to_aozora_note(c) {
+            len += c.len_utf8();
+        } else {
+            break;
+        }
+    }
+    if len == 0 {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::TakeWhile1,
+        )));
+    }
+    Ok(input.take_split(len))
+}
+
+fn nested_gaiji_len(input: &str) -> Option<usize> {
+    let note = input
+        .strip_prefix(character::is_gaiji_mark)?
+        .strip_prefix(character::is_start_aozora_note)?
+        .strip_prefix(character::is_aozora_note_mark)?;
+    let end = note.find(|c| !character::is_able_to_aozora_note(c))?;
+    note[end..]
+        .starts_with(character::is_end_aozora_note)
+        .then(|| input.len() - note.len() + end + '］'.len_utf8())
+}
+
+pub fn able_to_aozora_ruby(input: token::ParsedSpan) -> NomIResult {
+    take_while1(complete::is_able_to_aozora_ruby)(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,4 +299,27 @@ mod tests {
     fn end_emphasis_mark_works(input: &str) -> nom::IResult<token::ParsedSpan, token::ParsedSpan> {
         end_emphasis_mark(token::ParsedSpan::new(input))
     }
+
+    #[test_case("［＃改ページ］"=> Ok((token::test_helper::new_test_result_span(6, 1, "改ページ］"),token::test_helper::new_test_result_span(0, 1, "［＃"))))]
+    #[test_case("［改ページ］"=> Err(nom::Err::Error(nom::error::Error::new(
+            token::test_helper::new_test_result_span(3, 1, "改ページ］"),
+            nom::error::ErrorKind::TakeWhileMN,
+        ))))]
+    fn start_aozora_note_works(input: &str) -> nom::IResult<token::ParsedSpan, token::ParsedSpan> {
+        start_aozora_note(token::ParsedSpan::new(input))
+    }
+
+    #[test_case("改ページ］"=> Ok((token::test_helper::new_test_result_span(12, 1, "］"),token::test_helper::new_test_result_span(0, 1, "改ページ")));"note")]
+    #[test_case("「※［＃米印、1-2-8］」に傍点］"=> Ok((token::test_helper::new_test_result_span(41, 1, "］"),token::test_helper::new_test_result_span(0, 1, "「※［＃米印、1-2-8］」に傍点")));"nested_gaiji")]
+    #[test_case("※［＃米印］"=> Ok((token::test_helper::new_test_result_span(18, 1, ""),token::test_helper::new_test_result_span(0, 1, "※［＃米印］")));"gaiji_only")]
+    #[test_case("※［＃米印"=> Ok((token::test_helper::new_test_result_span(15, 1, ""),token::test_helper::new_test_result_span(0, 1, "※［＃米印")));"unclosed_gaiji")]
+    #[test_case("］"=> Err(nom::Err::Error(nom::error::Error::new(
+            token::test_helper::new_test_result_span(0, 1, "］"),
+            nom::error::ErrorKind::TakeWhile1,
+        )));"empty")]
+    fn able_to_aozora_note_works(
+        input: &str,
+    ) -> nom::IResult<token::ParsedSpan, token::ParsedSpan> {
+        able_to_aozora_note(token::ParsedSpan::new(input))
+    }
 }
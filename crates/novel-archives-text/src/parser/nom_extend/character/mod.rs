@@ -8,6 +8,10 @@ pub fn is_any_space(c: char) -> bool {
 pub fn is_start_directive(c: char) -> bool {
     c == '|' || c == '｜'
 }
+
+pub fn is_mono_ruby_separator(c: char) -> bool {
+    c == '・' || c == '･' || is_any_space(c)
+}
 pub fn is_start_annotation(c: char) -> bool {
     c == '$' || c == '＄'
 }
@@ -24,6 +28,10 @@ pub fn is_end_ruby(c: char) -> bool {
     c == '》' || c == '⟫' || c == ')' || c == '）'
 }
 
+pub fn is_start_parenthesis_ruby(c: char) -> bool {
+    c == '(' || c == '（'
+}
+
 pub fn is_start_term(c: char) -> bool {
     c == '"' || c == '”'
 }
@@ -148,6 +156,52 @@ pub fn is_plaintext(c: char) -> bool {
         || is_kanji(c))
 }
 
+pub fn is_gaiji_mark(c: char) -> bool {
+    c == '※'
+}
+
+pub fn is_start_aozora_note(c: char) -> bool {
+    c == '［'
+}
+
+pub fn is_aozora_note_mark(c: char) -> bool {
+    c == '＃'
+}
+
+pub fn is_end_aozora_note(c: char) -> bool {
+    c == '］'
+}
+
+pub fn is_able_to_aozora_note(c: char) -> bool {
+    !(is_any_newline(c) || is_end_aozora_note(c))
+}
+
+pub fn is_start_aozora_ruby(c: char) -> bool {
+    c == '《'
+}
+
+pub fn is_end_aozora_ruby(c: char) -> bool {
+    c == '》'
+}
+
+pub fn is_able_to_aozora_ruby(c: char) -> bool {
+    !(is_any_newline(c) || is_end_aozora_ruby(c))
+}
+
+pub fn is_able_to_aozora_ruby_body(c: char) -> bool {
+    !(is_any_newline(c) || is_start_aozora_ruby(c))
+}
+
+pub fn is_aozora_plaintext(c: char) -> bool {
+    !(is_start_directive(c)
+        || is_any_space(c)
+        || is_start_aozora_note(c)
+        || is_gaiji_mark(c)
+        || is_start_aozora_ruby(c)
+        || is_any_newline(c)
+        || is_kanji(c))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,6 +238,17 @@ mod tests {
         is_start_directive(c)
     }
 
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case('・'=>true;"wide_dot")]
+    #[test_case('･'=>true;"half_dot")]
+    #[test_case(' '=>true;"half_space")]
+    #[test_case('　'=>true;"wide_space")]
+    #[test_case('ー'=>false)]
+    #[test_case('あ'=>false)]
+    fn is_mono_ruby_separator_works(c: char) -> bool {
+        is_mono_ruby_separator(c)
+    }
+
     #[allow(clippy::bool_assert_comparison)]
     #[test_case('$'=>true;"half_$")]
     #[test_case('＄'=>true;"wide_＄")]
@@ -492,4 +557,17 @@ mod tests {
     fn is_able_to_ruby_body_works(c: char) -> bool {
         is_able_to_ruby_body(c)
     }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case('［'=>false;"note_start")]
+    #[test_case('※'=>false;"gaiji_mark")]
+    #[test_case('《'=>false;"ruby_start")]
+    #[test_case('｜'=>false;"wide_pipe")]
+    #[test_case('漢'=>false)]
+    #[test_case('(' =>true;"half_(")]
+    #[test_case('"' =>true;"quote")]
+    #[test_case('あ'=>true)]
+    fn is_aozora_plaintext_works(c: char) -> bool {
+        is_aozora_plaintext(c)
+    }
 }
@@ -0,0 +1,331 @@
+use super::*;
+use nom::bytes::complete::{take_while1, take_while_m_n};
+use nom::sequence::{pair, tuple};
+use nom::InputTake;
+use nom_extend::character;
+use nom_extend::character::complete;
+
+const BACK_REFERENCE_PARTICLE: &str = "に";
+const BACK_REFERENCE_START: char = '「';
+const BACK_REFERENCE_END: char = '」';
+
+pub fn ruby(input: ParsedSpan) -> IResult {
+    let (after_parsed_directive, directive) = complete::start_directive(input)?;
+    pair(
+        take_while1(character::is_able_to_aozora_ruby_body),
+        tuple((
+            take_while_m_n(1, 1, character::is_start_aozora_ruby),
+            complete::able_to_aozora_ruby,
+            take_while_m_n(1, 1, character::is_end_aozora_ruby),
+        )),
+    )(after_parsed_directive)
+    .map(|(input, (body, (start, ruby, end)))| {
+        (
+            input,
+            ParsedToken::Ruby {
+                directive,
+                body,
+                ruby,
+                delimiter: ParsedDelimiter::new(start, end),
+            },
+        )
+    })
+}
+
+pub fn kanji_ruby(input: ParsedSpan) -> IResult {
+    let (input, body) = complete::kanji1(input)?;
+    match tuple((
+        take_while_m_n(1, 1, character::is_start_aozora_ruby),
+        complete::able_to_aozora_ruby,
+        take_while_m_n(1, 1, character::is_end_aozora_ruby),
+    ))(input)
+    {
+        Ok((input, (start, ruby, end))) => Ok((
+            input,
+            ParsedToken::KanjiRuby {
+                body,
+                ruby,
+                delimiter: ParsedDelimiter::new(start, end),
+            },
+        )),
+        Err(_) => Ok((input, ParsedToken::Plaintext(body))),
+    }
+}
+
+pub fn emphasis_mark<'a>(context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+    let (after_parsed_start, (note_start, name, note_end)) = tuple((
+        complete::start_aozora_note,
+        complete::able_to_aozora_note,
+        take_while_m_n(1, 1, character::is_end_aozora_note),
+    ))(input)?;
+    let style = EmphasisStyle::from_aozora_name(name.fragment())
+        .ok_or_else(|| new_error(input, nom::error::ErrorKind::Verify))?;
+
+    let closing = format!(
+        "{}{}終わり{}",
+        note_start.fragment(),
+        name.fragment(),
+        note_end.fragment()
+    );
+    let rest = after_parsed_start.fragment();
+    let line = &rest[..rest.find(character::is_any_newline).unwrap_or(rest.len())];
+    match line.find(&closing) {
+        Some(body_len) if body_len > 0 => {
+            let (after_parsed_body, body) = after_parsed_start.take_split(body_len);
+            let (after_parsed_end, end) = after_parsed_body.take_split(closing.len());
+            let (_, start) = input.take_split(
+                note_start.fragment().len() + name.fragment().len() + note_end.fragment().len(),
+            );
+            Ok((
+                after_parsed_end,
+                ParsedToken::EmphasisMark {
+                    body: iterator::TextIterator::new(context.clone(), body).collect(),
+                    style,
+                    delimiter: ParsedDelimiter::new(start, end),
+                },
+            ))
+        }
+        _ => Err(new_error(input, nom::error::ErrorKind::TakeUntil)),
+    }
+}
+
+pub fn note<'a>(context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+    let (after_parsed_note, (start, description, end)) = tuple((
+        complete::start_aozora_note,
+        complete::able_to_aozora_note,
+        take_while_m_n(1, 1, character::is_end_aozora_note),
+    ))(input)?;
+    let empty = input.take(0);
+    if let Some(style) = back_reference_style(description.fragment()) {
+        let note_len = after_parsed_note.location_offset() - input.location_offset();
+        let (_, note) = input.take_split(note_len);
+        Ok((
+            after_parsed_note,
+            ParsedToken::EmphasisMark {
+                body: vec![],
+                style,
+                delimiter: ParsedDelimiter::new(empty, note),
+            },
+        ))
+    } else {
+        Ok((
+            after_parsed_note,
+            ParsedToken::Annotation {
+                directive: empty,
+                body: iterator::TextIterator::new(context.clone(), empty),
+                description: iterator::TextIterator::new(context.clone(), description),
+                delimiter: ParsedDelimiter::new(start, end),
+            },
+        ))
+    }
+}
+
+pub fn gaiji<'a>(context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+    tuple((
+        take_while_m_n(1, 1, character::is_gaiji_mark),
+        complete::start_aozora_note,
+        complete::able_to_aozora_note,
+        take_while_m_n(1, 1, character::is_end_aozora_note),
+    ))(input)
+    .map(|(after_parsed_note, (body, start, description, end))| {
+        (
+            after_parsed_note,
+            ParsedToken::Annotation {
+                directive: input.take(0),
+                body: iterator::TextIterator::new(context.clone(), body),
+                description: iterator::TextIterator::new(context.clone(), description),
+                delimiter: ParsedDelimiter::new(start, end),
+            },
+        )
+    })
+}
+
+pub fn other(input: ParsedSpan) -> IResult {
+    take_while_m_n(1, 1, |c| {
+        character::is_start_directive(c)
+            || character::is_start_aozora_note(c)
+            || character::is_gaiji_mark(c)
+            || character::is_start_aozora_ruby(c)
+    })(input)
+    .map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
+}
+
+pub fn plaintext(input: ParsedSpan) -> IResult {
+    take_while1(character::is_aozora_plaintext)(input)
+        .map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
+}
+
+pub fn back_reference_target<'b>(token: &'b ParsedToken) -> Option<&'b str> {
+    match token {
+        ParsedToken::EmphasisMark {
+            body, delimiter, ..
+        } if body.is_empty() && delimiter.start().fragment().is_empty() => {
+            let note = delimiter.end().fragment();
+            let (_, start) = complete::start_aozora_note(*delimiter.end()).ok()?;
+            back_reference(&note[start.fragment().len()..])
+        }
+        ParsedToken::Annotation {
+            directive,
+            body,
+            description,
+            ..
+        } if directive.fragment().is_empty() && body.input().fragment().is_empty() => {
+            back_reference(description.input().fragment())
+        }
+        _ => None,
+    }
+}
+
+pub fn resolve_back_reference<'a>(
+    token: ParsedToken<'a>,
+    target: ParsedSpan<'a>,
+) -> ParsedToken<'a> {
+    match token {
+        ParsedToken::EmphasisMark {
+            style, delimiter, ..
+        } => ParsedToken::EmphasisMark {
+            body: vec![ParsedToken::Plaintext(target)],
+            style,
+            delimiter: ParsedDelimiter::new(target.take(0), *delimiter.end()),
+        },
+        ParsedToken::Annotation {
+            body,
+            description,
+            delimiter,
+            ..
+        } => ParsedToken::Annotation {
+            directive: target.take(0),
+            body: iterator::TextIterator::new(body.context().clone(), target),
+            description,
+            delimiter,
+        },
+        token => token,
+    }
+}
+
+pub fn unresolved_back_reference<'a>(
+    context: &ParseContext,
+    token: ParsedToken<'a>,
+) -> ParsedToken<'a> {
+    match token {
+        ParsedToken::EmphasisMark { delimiter, .. } => {
+            context.report(DiagnosticKind::UnresolvedReference, *delimiter.end());
+            ParsedToken::Plaintext(*delimiter.end())
+        }
+        ParsedToken::Annotation {
+            ref description, ..
+        } => {
+            context.report(DiagnosticKind::UnresolvedReference, *description.input());
+            token
+        }
+        token => token,
+    }
+}
+
+fn back_reference_style(note: &str) -> Option<EmphasisStyle> {
+    let target = back_reference(note)?;
+    let name =
+        &note[BACK_REFERENCE_START.len_utf8() + target.len() + BACK_REFERENCE_END.len_utf8()..];
+    EmphasisStyle::from_aozora_name(name.strip_prefix(BACK_REFERENCE_PARTICLE).unwrap_or(name))
+}
+
+fn back_reference(note: &str) -> Option<&str> {
+    let note = note.strip_prefix(BACK_REFERENCE_START)?;
+    let target = &note[..note.find(BACK_REFERENCE_END)?];
+    if target.is_empty() {
+        None
+    } else {
+        Some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use test_case::test_case;
+
+    #[test_case("｜本文《ほんぶん》"=> Ok((token::test_helper::new_test_result_span(27, 1, ""),
+    ParsedToken::Ruby{
+        directive: token::test_helper::new_test_result_span(0, 1, "｜"),
+        body: token::test_helper::new_test_result_span(3, 1, "本文"),
+        ruby: token::test_helper::new_test_result_span(12, 1, "ほんぶん"),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(9, 1, "《"),token::test_helper::new_test_result_span(24, 1, "》")),
+    })))]
+    #[test_case("｜本文(ほんぶん)"=> Err(new_error(token::test_helper::new_test_result_span(23, 1, ""),nom::error::ErrorKind::TakeWhileMN));"parenthesis")]
+    fn ruby_works(input: &str) -> IResult {
+        ruby(token::ParsedSpan::new(input))
+    }
+
+    #[test_case("［＃傍点］本文［＃傍点終わり］"=> Ok((token::test_helper::new_test_result_span(45, 1, ""),
+    ParsedToken::EmphasisMark{
+        body: vec![ParsedToken::Plaintext(token::test_helper::new_test_result_span(15, 1, "本文"))],
+        style: EmphasisStyle::Sesame,
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "［＃傍点］"),token::test_helper::new_test_result_span(21, 1, "［＃傍点終わり］")),
+    })))]
+    #[test_case("［＃白ゴマ傍点］本文［＃白ゴマ傍点終わり］"=> Ok((token::test_helper::new_test_result_span(63, 1, ""),
+    ParsedToken::EmphasisMark{
+        body: vec![ParsedToken::Plaintext(token::test_helper::new_test_result_span(24, 1, "本文"))],
+        style: EmphasisStyle::OpenSesame,
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "［＃白ゴマ傍点］"),token::test_helper::new_test_result_span(30, 1, "［＃白ゴマ傍点終わり］")),
+    }));"style")]
+    #[test_case("［＃傍点］本文\n［＃傍点終わり］"=> Err(new_error(token::test_helper::new_test_result_span(0, 1, "［＃傍点］本文\n［＃傍点終わり］"),nom::error::ErrorKind::TakeUntil));"across_line")]
+    #[test_case("［＃改ページ］"=> Err(new_error(token::test_helper::new_test_result_span(0, 1, "［＃改ページ］"),nom::error::ErrorKind::Verify));"not_emphasis")]
+    fn emphasis_mark_works(input: &str) -> IResult {
+        emphasis_mark(
+            &ParseContext::default().with_dialect(Arc::new(dialect::Aozora)),
+            token::ParsedSpan::new(input),
+        )
+    }
+
+    fn delimiter(start: &'static str, end: &'static str) -> Delimiter<'static> {
+        Delimiter::new(start.into(), end.into())
+    }
+
+    #[test_case("｜青空《あおぞら》文庫"=>TokenText::new(vec![
+        Token::new_ruby("｜".into(),Span::new("青空".into(),Position::new(1,3).with_columns(2,2,2)),Span::new("あおぞら".into(),Position::new(1,12).with_columns(5,5,5)),delimiter("《","》")),
+        Token::new_plaintext(Span::new("文庫".into(),Position::new(1,27).with_columns(10,10,10))),
+    ]);"ruby")]
+    #[test_case("青空《あおぞら》(かっこ)"=>TokenText::new(vec![
+        Token::new_kanji_ruby(Span::new("青空".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("あおぞら".into(),Position::new(1,9).with_columns(4,4,4)),delimiter("《","》")),
+        Token::new_plaintext(Span::new("(かっこ)".into(),Position::new(1,24).with_columns(9,9,9))),
+    ]);"kanji_ruby")]
+    #[test_case("それは本文［＃「本文」に傍点］だ"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("それは".into(),Position::new(1,0).with_columns(1,1,1))),
+        Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("本文".into(),Position::new(1,9).with_columns(4,4,4)))]),EmphasisStyle::Sesame,delimiter("","［＃「本文」に傍点］")),
+        Token::new_plaintext(Span::new("だ".into(),Position::new(1,45).with_columns(16,16,16))),
+    ]);"back_reference_emphasis_mark")]
+    #[test_case("本文［＃「本文」に傍線］"=>TokenText::new(vec![
+        Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("本文".into(),Position::new(1,0).with_columns(1,1,1)))]),EmphasisStyle::SideLine,delimiter("","［＃「本文」に傍線］")),
+    ]);"back_reference_side_line")]
+    #[test_case("２０［＃「２０」は縦中横］"=>TokenText::new(vec![
+        Token::new_annotation("".into(),TokenText::new(vec![Token::new_plaintext(Span::new("２０".into(),Position::new(1,0).with_columns(1,1,1)))]),TokenText::new(vec![
+            Token::new_plaintext(Span::new("「２０」は縦中横".into(),Position::new(1,12).with_columns(5,5,5))),
+        ]),delimiter("［＃","］")),
+    ]);"back_reference_annotation")]
+    #[test_case("［＃「ない」に傍点］"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("［＃「ない」に傍点］".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"back_reference_not_found")]
+    #[test_case("本文［＃「ない」に傍点］"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("本文".into(),Position::new(1,0).with_columns(1,1,1))),
+        Token::new_plaintext(Span::new("［＃「ない」に傍点］".into(),Position::new(1,6).with_columns(3,3,3))),
+    ]);"back_reference_mismatch")]
+    #[test_case("［＃改ページ］\n"=>TokenText::new(vec![
+        Token::new_annotation("".into(),TokenText::new(vec![]),TokenText::new(vec![
+            Token::new_plaintext(Span::new("改ページ".into(),Position::new(1,6).with_columns(3,3,3))),
+        ]),delimiter("［＃","］")),
+        Token::new_new_line(Span::new("\n".into(),Position::new(1,21).with_columns(8,8,8))),
+    ]);"page_break")]
+    #[test_case("※［＃「木＋吶のつくり」、第3水準1-85-54］"=>TokenText::new(vec![
+        Token::new_annotation("".into(),TokenText::new(vec![Token::new_plaintext(Span::new("※".into(),Position::new(1,0).with_columns(1,1,1)))]),TokenText::new(vec![
+            Token::new_plaintext(Span::new("「木＋吶のつくり」、第3水準1-85-54".into(),Position::new(1,9).with_columns(4,4,4))),
+        ]),delimiter("［＃","］")),
+    ]);"gaiji")]
+    fn aozora_token_works(input: &str) -> TokenText {
+        iterator::TextIterator::new(
+            ParseContext::new(Arc::new(BTreeMap::new())).with_dialect(Arc::new(dialect::Aozora)),
+            token::ParsedSpan::new(input),
+        )
+        .collect()
+    }
+}
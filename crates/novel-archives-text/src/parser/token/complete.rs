@@ -1,6 +1,8 @@
 use super::*;
 use nom::bytes::complete::{take_while, take_while1, take_while_m_n};
-use nom::sequence::{delimited, pair};
+use nom::combinator::recognize;
+use nom::sequence::{pair, tuple};
+use nom::{InputTake, Slice};
 use nom_extend::character;
 use nom_extend::character::complete;
 
@@ -12,30 +14,202 @@ pub fn kanji(input: ParsedSpan) -> IResult {
     complete::kanji1(input).map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
 }
 
-const MAX_RUBY_COUNT_PER_BODY_CHAR: usize = 10;
-const MAX_RUBY_COUNT_BODY: usize = 10;
 pub fn kanji_ruby(input: ParsedSpan) -> IResult {
+    kanji_ruby_with(input, ParseContext::empty())
+}
+
+pub fn kanji_ruby_with<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    let options = context.options();
+    if !options.kanji_ruby() {
+        return kanji(input);
+    }
     let (input, body) = complete::kanji1(input)?;
-    let mut ruby_parser = delimited(
-        take_while_m_n(1, 1, character::is_start_ruby),
-        complete::able_to_ruby,
-        take_while_m_n(1, 1, character::is_end_ruby),
-    );
-    match ruby_parser(input) {
-        Ok((forword_input, ruby)) => {
+    let parsed: IResult<(ParsedSpan, ParsedSpan, ParsedSpan)> = tuple((
+        take_while_m_n(1, 1, |c| options.is_start_ruby(c)),
+        take_while1(|c| !(character::is_any_newline(c) || options.is_end_ruby(c))),
+        take_while_m_n(1, 1, |c| options.is_end_ruby(c)),
+    ))(input);
+    match parsed {
+        Ok((forword_input, (start, ruby, end))) => {
             let body_count = without_variation_selector_count(body.fragment());
-            if body_count <= MAX_RUBY_COUNT_BODY {
+            if body_count <= *options.max_ruby_count_body() {
                 let ruby_count = without_variation_selector_count(ruby.fragment());
-                if ruby_count <= MAX_RUBY_COUNT_PER_BODY_CHAR * body_count {
-                    return Ok((forword_input, ParsedToken::KanjiRuby { body, ruby }));
+                if ruby_count <= options.max_ruby_count_per_body_char() * body_count {
+                    let delimiter = ParsedDelimiter::new(start, end);
+                    return Ok((
+                        forword_input,
+                        match mono_ruby_pairs(body, ruby, options) {
+                            Some(pairs) => ParsedToken::MonoRuby {
+                                body,
+                                ruby,
+                                pairs,
+                                delimiter,
+                            },
+                            None => ParsedToken::KanjiRuby {
+                                body,
+                                ruby,
+                                delimiter,
+                            },
+                        },
+                    ));
                 }
             }
+            context.report(DiagnosticKind::RubyTooLong, ruby);
             Ok((input, ParsedToken::Plaintext(body)))
         }
-        Err(_) => Ok((input, ParsedToken::Plaintext(body))),
+        Err(_) => {
+            report_unclosed_ruby(context, input);
+            Ok((input, ParsedToken::Plaintext(body)))
+        }
+    }
+}
+
+fn mono_ruby_pairs<'a>(
+    body: ParsedSpan<'a>,
+    ruby: ParsedSpan<'a>,
+    options: &ParserOptions,
+) -> Option<Vec<ParsedRubyPair<'a>>> {
+    let readings = if ruby.fragment().contains(character::is_mono_ruby_separator) {
+        if !options.mono_ruby() {
+            return None;
+        }
+        split_span(ruby, character::is_mono_ruby_separator, |_| false)
+    } else if *options.split_group_ruby() {
+        split_span(ruby, |_| false, |_| true)
+    } else {
+        return None;
+    };
+    let bodies = split_span(
+        body,
+        |_| false,
+        |c| !character::is_kanji_variation_selector(c),
+    );
+    if bodies.len() < 2
+        || bodies.len() != readings.len()
+        || readings.iter().any(|reading| reading.fragment().is_empty())
+    {
+        return None;
+    }
+    Some(
+        bodies
+            .into_iter()
+            .zip(readings)
+            .map(|(body, ruby)| ParsedRubyPair::new(body, ruby))
+            .collect(),
+    )
+}
+
+fn split_span(
+    input: ParsedSpan,
+    is_separator: impl Fn(char) -> bool,
+    is_piece_start: impl Fn(char) -> bool,
+) -> Vec<ParsedSpan> {
+    let mut pieces = vec![];
+    let mut start = 0;
+    for (offset, c) in input.fragment().char_indices() {
+        if is_separator(c) {
+            pieces.push(input.slice(start..offset));
+            start = offset + c.len_utf8();
+        } else if is_piece_start(c) && offset > start {
+            pieces.push(input.slice(start..offset));
+            start = offset;
+        }
+    }
+    pieces.push(input.slice(start..));
+    pieces
+}
+
+fn report_unclosed_ruby(context: &ParseContext, input: ParsedSpan) {
+    let options = context.options();
+    let parsed: IResult<(ParsedSpan, ParsedSpan)> = pair(
+        take_while_m_n(1, 1, |c| options.is_start_ruby(c)),
+        take_while(|c| !(character::is_any_newline(c) || options.is_end_ruby(c))),
+    )(input);
+    if let Ok((rest, (start, _))) = parsed {
+        if !rest.fragment().starts_with(|c| options.is_end_ruby(c)) {
+            context.report(DiagnosticKind::UnclosedDelimiter, start);
+        }
     }
 }
 
+pub fn start_directive_with<'a>(
+    input: ParsedSpan<'a>,
+    options: &ParserOptions,
+) -> IResult<'a, ParsedSpan<'a>> {
+    take_while_m_n(1, 1, |c| options.is_start_directive(c))(input)
+}
+
+pub fn escape(input: ParsedSpan) -> IResult {
+    escape_with(input, ParseContext::empty())
+}
+
+pub fn escape_with<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    let options = context.options();
+    if !options.escape() {
+        return Err(new_error(input, nom::error::ErrorKind::Verify));
+    }
+    let parsed: IResult<(ParsedSpan, ParsedSpan)> = pair(
+        |input| start_directive_with(input, options),
+        take_while_m_n(1, 1, |c| options.is_escapable(c)),
+    )(input);
+    parsed.map(|(input, (directive, body))| (input, ParsedToken::Escape { directive, body }))
+}
+
+pub fn take_escaped1<'a>(
+    input: ParsedSpan<'a>,
+    context: &ParseContext,
+    is_stop: impl Fn(char) -> bool,
+) -> IResult<'a, ParsedSpan<'a>> {
+    let options = context.options();
+    let mut chars = input.fragment().char_indices().peekable();
+    let mut len = input.fragment().len();
+    while let Some((offset, c)) = chars.next() {
+        if *options.escape()
+            && options.is_start_directive(c)
+            && chars
+                .peek()
+                .is_some_and(|&(_, next)| options.is_escapable(next))
+        {
+            chars.next();
+        } else if is_stop(c) {
+            len = offset;
+            break;
+        }
+    }
+    if len == 0 {
+        return Err(new_error(input, nom::error::ErrorKind::TakeWhile1));
+    }
+    Ok(input.take_split(len))
+}
+
+pub fn escaped_text<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> Vec<ParsedToken<'a>> {
+    let options = context.options();
+    let mut tokens = vec![];
+    let mut rest = input;
+    while let Some(c) = rest.fragment().chars().next() {
+        if options.is_start_directive(c) {
+            if let Ok((after_parsed_escape, escape)) = escape_with(rest, context) {
+                tokens.push(escape);
+                rest = after_parsed_escape;
+                continue;
+            }
+        }
+        let len = rest
+            .fragment()
+            .char_indices()
+            .skip(1)
+            .find(|&(offset, c)| {
+                options.is_start_directive(c)
+                    && escape_with(rest.take_split(offset).0, context).is_ok()
+            })
+            .map_or(rest.fragment().len(), |(offset, _)| offset);
+        let (after_parsed_plaintext, plaintext) = rest.take_split(len);
+        tokens.push(ParsedToken::Plaintext(plaintext));
+        rest = after_parsed_plaintext;
+    }
+    tokens
+}
+
 pub fn space(input: ParsedSpan) -> IResult {
     complete::any_space1(input).map(|(input, parsed)| (input, ParsedToken::Space(parsed)))
 }
@@ -45,54 +219,168 @@ pub fn plaintext(input: ParsedSpan) -> IResult {
         .map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
 }
 
+pub fn plaintext_with<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    let options = context.options();
+    let fragment = input.fragment();
+    let mut len = 0;
+    loop {
+        len += fragment[len..]
+            .find(|c| !options.is_plaintext(c))
+            .unwrap_or(fragment.len() - len);
+        let rest = &fragment[len..];
+        if !rest.starts_with(character::is_kanji) {
+            break;
+        }
+        let kanji_len = rest
+            .find(|c| !character::is_kanji_related(c))
+            .unwrap_or(rest.len());
+        if *options.kanji_ruby() && rest[kanji_len..].starts_with(|c| options.is_start_ruby(c)) {
+            break;
+        }
+        len += kanji_len;
+    }
+    if len == 0 {
+        return Err(new_error(input, nom::error::ErrorKind::TakeWhile1));
+    }
+    let (input, parsed) = input.take_split(len);
+    Ok((input, ParsedToken::Plaintext(parsed)))
+}
+
 pub fn directive_ruby(input: ParsedSpan) -> IResult {
-    let (after_parsed_directive, directive) = complete::start_directive(input)?;
-    let (after_parsed_ruby, (body, ruby)) = pair(
-        take_while(character::is_able_to_ruby_body),
-        delimited(
-            take_while_m_n(1, 1, character::is_start_ruby),
-            complete::able_to_ruby,
-            take_while_m_n(1, 1, character::is_end_ruby),
-        ),
-    )(after_parsed_directive)?;
+    directive_ruby_with(input, ParseContext::empty())
+}
+
+pub fn directive_ruby_with<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    let options = context.options();
+    if !options.directive_ruby() {
+        return Err(new_error(input, nom::error::ErrorKind::Verify));
+    }
+    let (after_parsed_directive, directive) = start_directive_with(input, options)?;
+    let (after_parsed_body, body) =
+        take_while(|c| !(character::is_any_newline(c) || options.is_start_ruby(c)))(
+            after_parsed_directive,
+        )?;
+    let parsed: IResult<(ParsedSpan, ParsedSpan, ParsedSpan)> = tuple((
+        take_while_m_n(1, 1, |c| options.is_start_ruby(c)),
+        take_while1(|c| !(character::is_any_newline(c) || options.is_end_ruby(c))),
+        take_while_m_n(1, 1, |c| options.is_end_ruby(c)),
+    ))(after_parsed_body);
+    let (after_parsed_ruby, (start, ruby, end)) = parsed.inspect_err(|_| {
+        report_unclosed_ruby(context, after_parsed_body);
+    })?;
     if body.fragment().is_empty() {
+        context.report(DiagnosticKind::EmptyRubyBody, directive);
         Ok((after_parsed_directive, ParsedToken::Ignore(directive)))
     } else {
         let body_count = without_variation_selector_count(body.fragment());
-        let max_ruby_count = body_count * MAX_RUBY_COUNT_PER_BODY_CHAR;
+        let max_ruby_count = body_count * options.max_ruby_count_per_body_char();
 
         if without_variation_selector_count(ruby.fragment()) <= max_ruby_count {
-            Ok((after_parsed_ruby, ParsedToken::Ruby { body, ruby }))
+            Ok((
+                after_parsed_ruby,
+                ParsedToken::Ruby {
+                    directive,
+                    body,
+                    ruby,
+                    delimiter: ParsedDelimiter::new(start, end),
+                },
+            ))
         } else {
+            context.report(DiagnosticKind::RubyTooLong, ruby);
             Ok((after_parsed_directive, ParsedToken::Plaintext(directive)))
         }
     }
 }
 
 pub fn emphasis_mark(input: ParsedSpan) -> IResult {
-    delimited(
-        complete::start_emphasis_mark,
-        complete::able_to_emphasis_mark,
-        complete::end_emphasis_mark,
-    )(input)
-    .map(|(input, parsed)| (input, ParsedToken::EmphasisMark(parsed)))
+    emphasis_mark_with(input, ParseContext::empty())
 }
 
-pub fn directive_other(input: ParsedSpan) -> IResult {
-    take_while_m_n(1, 1, character::is_start_directive)(input)
-        .map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
+pub fn emphasis_mark_with<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    let options = context.options();
+    if !options.emphasis_mark() {
+        return Err(new_error(input, nom::error::ErrorKind::Verify));
+    }
+    let start: IResult<ParsedSpan> = recognize(pair(
+        take_while_m_n(1, 1, |c| options.is_start_emphasis_mark(c)),
+        take_while_m_n(1, 1, |c| options.is_start_emphasis_mark(c)),
+    ))(input);
+    let (after_parsed_start, start) = start?;
+    let (after_parsed_start, start, style) = match emphasis_style(after_parsed_start, options) {
+        Some((after_parsed_style, style_len, style)) => {
+            let (_, start) = input.take_split(start.fragment().len() + style_len);
+            (after_parsed_style, start, style)
+        }
+        None => (after_parsed_start, start, EmphasisStyle::default()),
+    };
+    let parsed: IResult<(ParsedSpan, ParsedSpan)> = pair(
+        |input| {
+            take_escaped1(input, context, |c| {
+                character::is_any_newline(c) || options.is_end_emphasis_mark(c)
+            })
+        },
+        recognize(pair(
+            take_while_m_n(1, 1, |c| options.is_end_emphasis_mark(c)),
+            take_while_m_n(1, 1, |c| options.is_end_emphasis_mark(c)),
+        )),
+    )(after_parsed_start);
+    let (input, (body, end)) = parsed.inspect_err(|_| {
+        if !after_parsed_start
+            .fragment()
+            .starts_with(|c| options.is_end_emphasis_mark(c))
+        {
+            context.report(DiagnosticKind::UnclosedDelimiter, start);
+        }
+    })?;
+    Ok((
+        input,
+        ParsedToken::EmphasisMark {
+            body: escaped_text(body, context),
+            style,
+            delimiter: ParsedDelimiter::new(start, end),
+        },
+    ))
 }
 
-pub fn term_directive_other(input: ParsedSpan) -> IResult {
-    take_while_m_n(1, 1, character::is_start_term)(input)
-        .map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
+fn emphasis_style<'a>(
+    input: ParsedSpan<'a>,
+    options: &ParserOptions,
+) -> Option<(ParsedSpan<'a>, usize, EmphasisStyle)> {
+    let parsed: IResult<(ParsedSpan, ParsedSpan)> = pair(
+        take_while1(|c| {
+            !(options.is_start_directive(c)
+                || character::is_any_newline(c)
+                || options.is_end_emphasis_mark(c))
+        }),
+        |input| start_directive_with(input, options),
+    )(input);
+    let (input, (name, directive)) = parsed.ok()?;
+    let style = EmphasisStyle::from_name(name.fragment())?;
+    Some((
+        input,
+        name.fragment().len() + directive.fragment().len(),
+        style,
+    ))
 }
 
-pub fn emphasis_mark_start_other(input: ParsedSpan) -> IResult {
-    take_while_m_n(1, 1, character::is_start_emphasis_mark)(input)
+pub fn directive_other<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    start_directive_with(input, context.options())
         .map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
 }
 
+pub fn term_directive_other<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    let options = context.options();
+    let parsed: IResult<ParsedSpan> = take_while_m_n(1, 1, |c| options.is_start_term(c))(input);
+    parsed.map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
+}
+
+pub fn emphasis_mark_start_other<'a>(input: ParsedSpan<'a>, context: &ParseContext) -> IResult<'a> {
+    let options = context.options();
+    let parsed: IResult<ParsedSpan> =
+        take_while_m_n(1, 1, |c| options.is_start_emphasis_mark(c))(input);
+    parsed.map(|(input, parsed)| (input, ParsedToken::Plaintext(parsed)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,6 +399,52 @@ mod tests {
         newline(token::ParsedSpan::new(input))
     }
 
+    #[test_case("|\"穂積\""=> Ok((token::test_helper::new_test_result_span(2, 1, "穂積\""),ParsedToken::Escape{
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
+        body: token::test_helper::new_test_result_span(1, 1, "\""),
+    }));"term")]
+    #[test_case("｜《《"=> Ok((token::test_helper::new_test_result_span(6, 1, "《"),ParsedToken::Escape{
+        directive: token::test_helper::new_test_result_span(0, 1, "｜"),
+        body: token::test_helper::new_test_result_span(3, 1, "《"),
+    }));"emphasis_mark")]
+    #[test_case("||"=> Ok((token::test_helper::new_test_result_span(2, 1, ""),ParsedToken::Escape{
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
+        body: token::test_helper::new_test_result_span(1, 1, "|"),
+    }));"directive")]
+    #[test_case("|あ"=> Err(new_error(token::test_helper::new_test_result_span(1, 1, "あ"),nom::error::ErrorKind::TakeWhileMN));"not_escapable")]
+    fn escape_works(input: &str) -> IResult {
+        escape(token::ParsedSpan::new(input))
+    }
+
+    #[test_case("本文|》》》》"=>Ok(("》》》", "本文|》")))]
+    #[test_case("》》"=>Err(()))]
+    fn take_escaped1_works(input: &str) -> Result<(&str, &str), ()> {
+        take_escaped1(
+            token::ParsedSpan::new(input),
+            &ParseContext::default(),
+            character::is_end_emphasis_mark,
+        )
+        .map(|(rest, parsed)| (*rest.fragment(), *parsed.fragment()))
+        .map_err(|_| ())
+    }
+
+    #[test_case("本文"=>vec![("", "本文")];"plaintext")]
+    #[test_case("本|》文"=>vec![("", "本"), ("|", "》"), ("", "文")];"escape")]
+    #[test_case("|》|》"=>vec![("|", "》"), ("|", "》")];"escapes")]
+    #[test_case("本|文|"=>vec![("", "本|文|")];"not_escapable")]
+    fn escaped_text_works(input: &str) -> Vec<(&str, &str)> {
+        escaped_text(token::ParsedSpan::new(input), &ParseContext::default())
+            .into_iter()
+            .map(|token| match token {
+                ParsedToken::Escape { directive, body } => {
+                    (*directive.fragment(), *body.fragment())
+                }
+                ParsedToken::Plaintext(body) => ("", *body.fragment()),
+                token => panic!("unexpected token {:?}", token),
+            })
+            .collect()
+    }
+
     #[test_case("漢字"=> Ok((token::test_helper::new_test_result_span(6, 1, ""),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "漢字")))))]
     #[test_case("漢字とひらがな"=> Ok((token::test_helper::new_test_result_span(6, 1, "とひらがな"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "漢字")))))]
     #[test_case("なか漢字なか"=> Err(new_error(token::test_helper::new_test_result_span(0, 1, "なか漢字なか"),nom::error::ErrorKind::TakeWhile1)))]
@@ -126,35 +460,91 @@ mod tests {
         space(token::ParsedSpan::new(input))
     }
 
+    #[test_case("かな漢字かな|", true=> Ok((token::test_helper::new_test_result_span(18, 1, "|"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "かな漢字かな"))));"kanji_without_ruby")]
+    #[test_case("かな漢字(かんじ)", true=> Ok((token::test_helper::new_test_result_span(6, 1, "漢字(かんじ)"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "かな"))));"stop_before_kanji_ruby")]
+    #[test_case("かな漢字(かんじ)", false=> Ok((token::test_helper::new_test_result_span(23, 1, ""),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "かな漢字(かんじ)"))));"kanji_ruby_disabled")]
+    #[test_case("かな漢字　", true=> Ok((token::test_helper::new_test_result_span(12, 1, "　"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "かな漢字"))));"kanji_before_space")]
+    #[test_case("漢字(かんじ)", true=> Err(new_error(token::test_helper::new_test_result_span(0, 1, "漢字(かんじ)"),nom::error::ErrorKind::TakeWhile1));"kanji_ruby")]
+    fn plaintext_with_works(input: &str, kanji_ruby: bool) -> IResult {
+        let context = ParseContext::default()
+            .with_options(ParserOptions::default().with_kanji_ruby(kanji_ruby));
+        plaintext_with(token::ParsedSpan::new(input), &context)
+    }
+
     #[test_case("漢字"=> Ok((token::test_helper::new_test_result_span(6, 1, ""),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "漢字")))))]
     #[test_case("漢字|(かんじ)"=> Ok((token::test_helper::new_test_result_span(6, 1, "|(かんじ)"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "漢字")))))]
     #[test_case("漢字(かんじ)"=> Ok((token::test_helper::new_test_result_span(17, 1, ""),ParsedToken::KanjiRuby{body:token::test_helper::new_test_result_span(0, 1, "漢字"),
-    ruby:test_helper::new_test_result_span(7, 1, "かんじ")}));"half")]
+    ruby:test_helper::new_test_result_span(7, 1, "かんじ"),
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(6, 1, "("),test_helper::new_test_result_span(16, 1, ")"))}));"half")]
     #[test_case("漢字漢字漢字漢字漢字字(かんじ)"=> Ok((token::test_helper::new_test_result_span(33, 1, "(かんじ)"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "漢字漢字漢字漢字漢字字")))))]
     #[test_case("邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄(なべなべなべなべなべ)"=> Ok((token::test_helper::new_test_result_span(102, 1, ""),ParsedToken::KanjiRuby{body:token::test_helper::new_test_result_span(0, 1, "邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄邊󠄄"),
-    ruby:test_helper::new_test_result_span(71, 1, "なべなべなべなべなべ")}));"nabe")]
+    ruby:test_helper::new_test_result_span(71, 1, "なべなべなべなべなべ"),
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(70, 1, "("),test_helper::new_test_result_span(101, 1, ")"))}));"nabe")]
     #[test_case("漢字（かんじ）"=> Ok((token::test_helper::new_test_result_span(21, 1, ""),ParsedToken::KanjiRuby{body:token::test_helper::new_test_result_span(0, 1, "漢字"),
-    ruby:test_helper::new_test_result_span(9, 1, "かんじ")}));"wide")]
+    ruby:test_helper::new_test_result_span(9, 1, "かんじ"),
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(6, 1, "（"),test_helper::new_test_result_span(18, 1, "）"))}));"wide")]
     #[test_case("漢字アイウエオ"=> Ok((token::test_helper::new_test_result_span(6, 1, "アイウエオ"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "漢字")))))]
     #[test_case("カタカナ"=> Err(new_error(token::test_helper::new_test_result_span(0, 1, "カタカナ"),nom::error::ErrorKind::TakeWhile1)))]
+    #[test_case("漢字(かん・じ)"=> Ok((token::test_helper::new_test_result_span(20, 1, ""),ParsedToken::MonoRuby{body:token::test_helper::new_test_result_span(0, 1, "漢字"),
+    ruby:test_helper::new_test_result_span(7, 1, "かん・じ"),
+    pairs:vec![
+        ParsedRubyPair::new(test_helper::new_test_result_span(0, 1, "漢"),test_helper::new_test_result_span(7, 1, "かん")),
+        ParsedRubyPair::new(test_helper::new_test_result_span(3, 1, "字"),test_helper::new_test_result_span(16, 1, "じ")),
+    ],
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(6, 1, "("),test_helper::new_test_result_span(19, 1, ")"))}));"mono_dot")]
+    #[test_case("邊󠄄字(なべ　じ)"=> Ok((token::test_helper::new_test_result_span(24, 1, ""),ParsedToken::MonoRuby{body:token::test_helper::new_test_result_span(0, 1, "邊󠄄字"),
+    ruby:test_helper::new_test_result_span(11, 1, "なべ　じ"),
+    pairs:vec![
+        ParsedRubyPair::new(test_helper::new_test_result_span(0, 1, "邊󠄄"),test_helper::new_test_result_span(11, 1, "なべ")),
+        ParsedRubyPair::new(test_helper::new_test_result_span(7, 1, "字"),test_helper::new_test_result_span(20, 1, "じ")),
+    ],
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(10, 1, "("),test_helper::new_test_result_span(23, 1, ")"))}));"mono_space_variation_selector")]
+    #[test_case("漢字(ジョン・ス・ミス)"=> Ok((token::test_helper::new_test_result_span(32, 1, ""),ParsedToken::KanjiRuby{body:token::test_helper::new_test_result_span(0, 1, "漢字"),
+    ruby:test_helper::new_test_result_span(7, 1, "ジョン・ス・ミス"),
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(6, 1, "("),test_helper::new_test_result_span(31, 1, ")"))}));"mono_count_mismatch")]
     fn kanji_ruby_works(input: &str) -> IResult {
         kanji_ruby(token::ParsedSpan::new(input))
     }
 
+    #[test_case("漢字(かじ)", true=> Ok((token::test_helper::new_test_result_span(14, 1, ""),ParsedToken::MonoRuby{body:token::test_helper::new_test_result_span(0, 1, "漢字"),
+    ruby:test_helper::new_test_result_span(7, 1, "かじ"),
+    pairs:vec![
+        ParsedRubyPair::new(test_helper::new_test_result_span(0, 1, "漢"),test_helper::new_test_result_span(7, 1, "か")),
+        ParsedRubyPair::new(test_helper::new_test_result_span(3, 1, "字"),test_helper::new_test_result_span(10, 1, "じ")),
+    ],
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(6, 1, "("),test_helper::new_test_result_span(13, 1, ")"))}));"split")]
+    #[test_case("漢字(かじ)", false=> Ok((token::test_helper::new_test_result_span(14, 1, ""),ParsedToken::KanjiRuby{body:token::test_helper::new_test_result_span(0, 1, "漢字"),
+    ruby:test_helper::new_test_result_span(7, 1, "かじ"),
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(6, 1, "("),test_helper::new_test_result_span(13, 1, ")"))}));"not_split")]
+    #[test_case("漢字(かんじ)", true=> Ok((token::test_helper::new_test_result_span(17, 1, ""),ParsedToken::KanjiRuby{body:token::test_helper::new_test_result_span(0, 1, "漢字"),
+    ruby:test_helper::new_test_result_span(7, 1, "かんじ"),
+    delimiter:ParsedDelimiter::new(test_helper::new_test_result_span(6, 1, "("),test_helper::new_test_result_span(16, 1, ")"))}));"count_mismatch")]
+    fn kanji_ruby_split_group_ruby_works(input: &str, split_group_ruby: bool) -> IResult {
+        let context = ParseContext::default()
+            .with_options(ParserOptions::default().with_split_group_ruby(split_group_ruby));
+        kanji_ruby_with(token::ParsedSpan::new(input), &context)
+    }
+
     #[test_case("|漢字(かんじ)"=> Ok((token::test_helper::new_test_result_span(18, 1, ""),
     ParsedToken::Ruby{
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
         body: token::test_helper::new_test_result_span(1, 1, "漢字"),
         ruby: token::test_helper::new_test_result_span(8, 1, "かんじ"),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(7, 1, "("),token::test_helper::new_test_result_span(17, 1, ")")),
     })))]
     #[test_case("|ほげ（ふが)"=> Ok((token::test_helper::new_test_result_span(17, 1, ""),
     ParsedToken::Ruby{
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
         body: token::test_helper::new_test_result_span(1, 1, "ほげ"),
         ruby: token::test_helper::new_test_result_span(10, 1, "ふが"),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(7, 1, "（"),token::test_helper::new_test_result_span(16, 1, ")")),
     })))]
     #[test_case("|ふ符(hoho）"=> Ok((token::test_helper::new_test_result_span(15, 1, ""),
     ParsedToken::Ruby{
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
         body: token::test_helper::new_test_result_span(1, 1, "ふ符"),
         ruby: token::test_helper::new_test_result_span(8, 1, "hoho"),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(7, 1, "("),token::test_helper::new_test_result_span(12, 1, "）")),
     })))]
     #[test_case("|(かんじ)"=> Ok((token::test_helper::new_test_result_span(1, 1, "(かんじ)"),ParsedToken::Ignore(token::test_helper::new_test_result_span(0, 1, "|"))));"half_directive")]
     #[test_case("｜(かんじ)"=> Ok((token::test_helper::new_test_result_span(3, 1, "(かんじ)"),ParsedToken::Ignore(token::test_helper::new_test_result_span(0, 1, "｜"))));"wide_directive")]
@@ -162,7 +552,32 @@ mod tests {
         directive_ruby(token::ParsedSpan::new(input))
     }
 
-    #[test_case("《《傍点確認》》"=> Ok((token::test_helper::new_test_result_span(24, 1, ""),ParsedToken::EmphasisMark(token::test_helper::new_test_result_span(6, 1, "傍点確認")))))]
+    #[test_case("《《傍点確認》》"=> Ok((token::test_helper::new_test_result_span(24, 1, ""),ParsedToken::EmphasisMark{
+        body: vec![ParsedToken::Plaintext(token::test_helper::new_test_result_span(6, 1, "傍点確認"))],
+        style: EmphasisStyle::Sesame,
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "《《"),token::test_helper::new_test_result_span(18, 1, "》》")),
+    })))]
+    #[test_case("《《白ゴマ|傍点》》"=> Ok((token::test_helper::new_test_result_span(28, 1, ""),ParsedToken::EmphasisMark{
+        body: vec![ParsedToken::Plaintext(token::test_helper::new_test_result_span(16, 1, "傍点"))],
+        style: EmphasisStyle::OpenSesame,
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "《《白ゴマ|"),token::test_helper::new_test_result_span(22, 1, "》》")),
+    }));"style")]
+    #[test_case("《《不明|傍点》》"=> Ok((token::test_helper::new_test_result_span(25, 1, ""),ParsedToken::EmphasisMark{
+        body: vec![ParsedToken::Plaintext(token::test_helper::new_test_result_span(6, 1, "不明|傍点"))],
+        style: EmphasisStyle::Sesame,
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "《《"),token::test_helper::new_test_result_span(19, 1, "》》")),
+    }));"unknown_style")]
+    #[test_case("《《a|》》》"=> Ok((token::test_helper::new_test_result_span(17, 1, ""),ParsedToken::EmphasisMark{
+        body: vec![
+            ParsedToken::Plaintext(token::test_helper::new_test_result_span(6, 1, "a")),
+            ParsedToken::Escape{
+                directive: token::test_helper::new_test_result_span(7, 1, "|"),
+                body: token::test_helper::new_test_result_span(8, 1, "》"),
+            },
+        ],
+        style: EmphasisStyle::Sesame,
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "《《"),token::test_helper::new_test_result_span(11, 1, "》》")),
+    }));"escaped_end")]
     #[test_case("《《傍点\n確認》》" => Err(new_error(token::test_helper::new_test_result_span(12, 1, "\n確認》》"),nom::error::ErrorKind::TakeWhileMN)))]
     #[test_case("《》《not傍点》は" => Err(new_error(token::test_helper::new_test_result_span(3, 1, "》《not傍点》は"),nom::error::ErrorKind::TakeWhileMN)))]
     fn emphasis_mark_works(input: &str) -> IResult {
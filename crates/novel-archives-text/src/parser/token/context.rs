@@ -1,100 +1,330 @@
-use nom::branch::alt;
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
 use nom::bytes::complete::{take_while1, take_while_m_n};
-use nom::sequence::{delimited, tuple};
-use nom_extend::character::complete;
-use std::cmp::Reverse;
-use std::collections::BTreeMap;
-use token::complete as token_complete;
+use nom::InputTake;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
 
 use super::*;
 
-#[derive(Debug, PartialEq, Clone, new)]
+#[derive(Debug, Clone)]
 pub struct ParseContext {
     term_map: Arc<BTreeMap<String, term::Term>>,
+    term_index: Arc<TermMatcher>,
+    dialect: Arc<dyn dialect::Dialect>,
+    options: Arc<ParserOptions>,
+    options_overridden: bool,
+    diagnostics: Option<Diagnostics>,
+}
+
+impl Default for ParseContext {
+    fn default() -> Self {
+        Self::empty().clone()
+    }
+}
+
+impl PartialEq for ParseContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.term_map == other.term_map
+            && *self.dialect == *other.dialect
+            && self.options == other.options
+    }
 }
 
 impl ParseContext {
+    pub fn new(term_map: Arc<BTreeMap<String, term::Term>>) -> Self {
+        Self {
+            term_index: Arc::new(TermMatcher::new(term_map.values().cloned().collect())),
+            term_map,
+            dialect: Arc::new(dialect::NovelArchives),
+            options: Arc::default(),
+            options_overridden: false,
+            diagnostics: None,
+        }
+    }
+
+    pub(crate) fn empty() -> &'static Self {
+        static EMPTY: OnceLock<ParseContext> = OnceLock::new();
+        EMPTY.get_or_init(|| Self::new(Arc::new(BTreeMap::new())))
+    }
+
+    pub fn with_dialect(self, dialect: Arc<dyn dialect::Dialect>) -> Self {
+        let options = if self.options_overridden {
+            self.options.clone()
+        } else {
+            Arc::new(dialect.options())
+        };
+        Self {
+            options,
+            dialect,
+            ..self
+        }
+    }
+
+    pub fn with_options(self, options: ParserOptions) -> Self {
+        Self {
+            options: Arc::new(options),
+            options_overridden: true,
+            ..self
+        }
+    }
+
+    pub fn with_diagnostics(self) -> Self {
+        Self {
+            diagnostics: Some(Diagnostics::default()),
+            ..self
+        }
+    }
+
+    pub fn dialect(&self) -> &dyn dialect::Dialect {
+        self.dialect.as_ref()
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        self.options.as_ref()
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .as_ref()
+            .map(Diagnostics::to_vec)
+            .unwrap_or_default()
+    }
+
+    pub fn report(&self, kind: DiagnosticKind, span: ParsedSpan) {
+        if let Some(diagnostics) = &self.diagnostics {
+            diagnostics.report(Diagnostic::from_kind(kind, span));
+        }
+    }
+
     pub fn term<'a>(&self, input: ParsedSpan<'a>) -> IResult<'a> {
-        let (input, parsed) = delimited(
-            take_while_m_n(1, 1, character::is_start_term),
-            complete::able_to_term,
-            take_while_m_n(1, 1, character::is_end_term),
-        )(input)?;
-        let term = self
-            .term_map
-            .get(*parsed.fragment())
-            .ok_or_else(|| new_error(input, nom::error::ErrorKind::TakeTill1))?;
-        Ok((
-            input,
-            ParsedToken::Term {
-                body: parsed,
-                term_id: term.id().clone(),
-            },
-        ))
+        if !self.options.term() {
+            return Err(new_error(input, nom::error::ErrorKind::Verify));
+        }
+        let options = self.options();
+        let (after_parsed_start, start) =
+            take_while_m_n(1, 1, |c| options.is_start_term(c))(input)?;
+        let (after_parsed_body, parsed) =
+            take_while1(|c| !(character::is_any_newline(c) || options.is_end_term(c)))(
+                after_parsed_start,
+            )?;
+        let end: IResult<ParsedSpan> =
+            take_while_m_n(1, 1, |c| options.is_end_term(c))(after_parsed_body);
+        let (after_parsed_end, end) = end.inspect_err(|_| {
+            self.report(DiagnosticKind::UnclosedDelimiter, start);
+        })?;
+        match self.term_index.get(parsed.fragment()) {
+            Some(term) => Ok((
+                after_parsed_end,
+                ParsedToken::Term {
+                    body: parsed,
+                    term_id: term.id().clone(),
+                    delimiter: ParsedDelimiter::new(start, end),
+                },
+            )),
+            None => {
+                self.report(DiagnosticKind::UnknownTerm, parsed);
+                let (_, quoted) =
+                    input.take_split(after_parsed_end.location_offset() - input.location_offset());
+                Ok((after_parsed_end, ParsedToken::Plaintext(quoted)))
+            }
+        }
+    }
+
+    pub fn find_unquoted_terms(&self, input: &str) -> Vec<(usize, usize, &term::Term)> {
+        if *self.options.unquoted_term() {
+            self.term_index.find_iter(input)
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn term_matcher(&self) -> &TermMatcher {
+        self.term_index.as_ref()
+    }
+
+    pub fn terms(&self) -> impl Iterator<Item = &term::Term> {
+        self.term_map.values()
+    }
+
+    fn description_context(&self) -> Self {
+        if *self.options.unquoted_term() {
+            self.clone()
+                .with_options(self.options().clone().with_unquoted_term(false))
+        } else {
+            self.clone()
+        }
     }
 
     pub fn token<'a>(&self, input: ParsedSpan<'a>) -> IResult<'a> {
-        alt((
-            |input| self.term(input),
-            |input| self.directive_annotation(input),
-            token_complete::kanji_ruby,
-            token_complete::directive_ruby,
-            token_complete::emphasis_mark,
-            token_complete::directive_other,
-            token_complete::term_directive_other,
-            token_complete::emphasis_mark_start_other,
-            token_complete::space,
-            token_complete::newline,
-            token_complete::plaintext,
-        ))(input)
+        self.dialect.token(self, input)
     }
 
     pub fn directive_annotation<'a>(&self, input: ParsedSpan<'a>) -> IResult<'a> {
-        tuple((
-            complete::start_directive,
-            take_while1(character::is_able_to_annotation_body),
-            delimited(
-                take_while_m_n(1, 1, character::is_start_annotation),
-                complete::able_to_annotation,
-                take_while_m_n(1, 1, character::is_end_annotation),
-            ),
-        ))(input)
-        .map(|(input, (_, body, description))| {
-            (
-                input,
-                ParsedToken::Annotation {
-                    body,
-                    description: iterator::TextIterator::new(self.clone(), description),
-                },
-            )
-        })
+        if !self.options.annotation() {
+            return Err(new_error(input, nom::error::ErrorKind::Verify));
+        }
+        let options = self.options();
+        let (after_parsed_directive, directive) =
+            super::complete::start_directive_with(input, options)?;
+        let (after_parsed_body, body) =
+            super::complete::take_escaped1(after_parsed_directive, self, |c| {
+                character::is_any_newline(c) || options.is_start_annotation(c)
+            })?;
+        let (after_parsed_start, start) =
+            take_while_m_n(1, 1, |c| options.is_start_annotation(c))(after_parsed_body)?;
+        let (after_parsed_description, description) =
+            super::complete::take_escaped1(after_parsed_start, self, |c| {
+                character::is_any_newline(c) || options.is_end_annotation(c)
+            })
+            .inspect_err(|_| {
+                if after_parsed_start
+                    .fragment()
+                    .starts_with(|c| options.is_end_annotation(c))
+                {
+                    self.report(DiagnosticKind::AnnotationWithoutDescription, body);
+                } else {
+                    self.report(DiagnosticKind::UnclosedDelimiter, start);
+                }
+            })?;
+        let end: IResult<ParsedSpan> =
+            take_while_m_n(1, 1, |c| options.is_end_annotation(c))(after_parsed_description);
+        let (input, end) = end.inspect_err(|_| {
+            self.report(DiagnosticKind::UnclosedDelimiter, start);
+        })?;
+        Ok((
+            input,
+            ParsedToken::Annotation {
+                directive,
+                body: iterator::TextIterator::new(self.description_context(), body),
+                description: iterator::TextIterator::new(self.description_context(), description),
+                delimiter: ParsedDelimiter::new(start, end),
+            },
+        ))
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct TermMap(Vec<(char, Vec<term::Term>)>);
+#[derive(Debug, Clone)]
+pub struct TermMatcher {
+    automaton: AhoCorasick,
+    patterns: Vec<usize>,
+    surfaces: HashMap<String, usize>,
+    terms: Vec<term::Term>,
+}
 
-impl TermMap {
+impl TermMatcher {
     pub fn new(terms: Vec<term::Term>) -> Self {
-        let mut term_map = Self(Vec::with_capacity(terms.len()));
-
-        for term in terms.iter() {
-            let ck = term.body().chars().next().unwrap();
-            match term_map.0.binary_search_by_key(&ck, |(k, _)| *k) {
-                Ok(i) => {
-                    let (_, terms) = term_map.0.get_mut(i).unwrap();
-                    terms.push(term.clone());
-                }
-                Err(_) => {
-                    term_map.0.push((ck, vec![term.clone()]));
-                    term_map.0.sort_by_key(|(k, _)| *k);
+        let mut surfaces = HashMap::new();
+        let mut patterns = vec![];
+        for (index, term) in terms.iter().enumerate() {
+            for surface in term.surface_forms() {
+                let surface = term::normalize(surface);
+                if !surface.is_empty() && !surfaces.contains_key(&surface) {
+                    surfaces.insert(surface.clone(), index);
+                    patterns.push((surface, index));
                 }
             }
         }
-        for (_, terms) in term_map.0.iter_mut() {
-            terms.sort_by_key(|kv| Reverse(without_variation_selector_count(kv.body())));
+        let automaton = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(patterns.iter().map(|(surface, _)| surface));
+        Self {
+            automaton,
+            patterns: patterns.into_iter().map(|(_, index)| index).collect(),
+            surfaces,
+            terms,
+        }
+    }
+
+    pub fn get(&self, body: &str) -> Option<&term::Term> {
+        self.surfaces
+            .get(&term::normalize(body))
+            .map(|&index| &self.terms[index])
+    }
+
+    // Returns non-overlapping (offset, len, term) matches, taking the leftmost and then
+    // the longest surface which does not cut a kanji compound.
+    pub fn find_iter(&self, input: &str) -> Vec<(usize, usize, &term::Term)> {
+        let normalized = NormalizedText::new(input);
+        let mut found = vec![];
+        let mut position = 0;
+        while let Some(m) = self.automaton.find(&normalized.text[position..]) {
+            let start = position + m.start();
+            let longest = (position + m.end(), self.patterns[m.pattern()]);
+            let matched = if normalized.is_in_compound(start) {
+                None
+            } else {
+                std::iter::once(longest)
+                    .chain(self.shorter_matches(&normalized, start, longest.0))
+                    .find(|&(end, _)| !normalized.is_in_compound(end))
+            };
+            position = match matched {
+                Some((end, index)) => {
+                    let (offset, end_offset) = (normalized.offsets[start], normalized.offsets[end]);
+                    found.push((offset, end_offset - offset, &self.terms[index]));
+                    end
+                }
+                None => normalized.next_boundary(start),
+            };
+        }
+        found
+    }
+
+    // Surfaces starting at `start` and ending before `end`, longest first.
+    fn shorter_matches(
+        &self,
+        normalized: &NormalizedText,
+        start: usize,
+        end: usize,
+    ) -> Vec<(usize, usize)> {
+        let text = &normalized.text[start..end];
+        text.char_indices()
+            .rev()
+            .filter(|&(len, _)| len > 0)
+            .filter_map(|(len, _)| {
+                self.surfaces
+                    .get(&text[..len])
+                    .map(|&index| (start + len, index))
+            })
+            .collect()
+    }
+}
+
+// The normalized input, with the input byte offset of every normalized char boundary.
+struct NormalizedText<'a> {
+    input: &'a str,
+    text: String,
+    offsets: Vec<usize>,
+}
+
+impl<'a> NormalizedText<'a> {
+    fn new(input: &'a str) -> Self {
+        let mut text = String::with_capacity(input.len());
+        let mut offsets = vec![0; input.len() + 1];
+        for (end, c) in term::normalized_chars(input) {
+            text.push(c);
+            offsets[text.len()] = end;
+        }
+        offsets.truncate(text.len() + 1);
+        Self {
+            input,
+            text,
+            offsets,
         }
-        term_map
+    }
+
+    // Whether the boundary splits a run of kanji in the input.
+    fn is_in_compound(&self, boundary: usize) -> bool {
+        let offset = self.offsets[boundary];
+        self.input[..offset].ends_with(character::is_kanji_related)
+            && self.input[offset..].starts_with(character::is_kanji_related)
+    }
+
+    fn next_boundary(&self, boundary: usize) -> usize {
+        boundary
+            + self.text[boundary..]
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8)
     }
 }
 
@@ -117,6 +347,7 @@ mod tests {
         => Ok((token::test_helper::new_test_result_span(5, 1, ""),ParsedToken::Term{
         body: token::test_helper::new_test_result_span(1, 1, "無"),
         term_id:Id::new("term_id1"),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "\""),token::test_helper::new_test_result_span(4, 1, "\"")),
         }))
     ;"quote無")]
     #[test_case(vec![
@@ -125,6 +356,7 @@ mod tests {
         => Ok((token::test_helper::new_test_result_span(8, 1, "しょう"),ParsedToken::Term{
         body: token::test_helper::new_test_result_span(1, 1, "穂積"),
         term_id:Id::new("term_id1"),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "\""),token::test_helper::new_test_result_span(7, 1, "\"")),
         }))
     )]
     #[test_case(vec![
@@ -144,8 +376,14 @@ mod tests {
         => Ok((token::test_helper::new_test_result_span(17, 1, ""),ParsedToken::Term{
         body: token::test_helper::new_test_result_span(1, 1, "穂積しょう"),
         term_id:Id::new("term_id1"),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(0, 1, "\""),token::test_helper::new_test_result_span(16, 1, "\"")),
         }))
     )]
+    #[test_case(vec![
+            new_sample_term("term_id1","穂積"),
+    ],"\"有\"と"
+        => Ok((token::test_helper::new_test_result_span(5, 1, "と"),ParsedToken::Plaintext(token::test_helper::new_test_result_span(0, 1, "\"有\""))))
+    ;"unknown")]
     fn context_term_works(terms: Vec<term::Term>, input: &str) -> IResult {
         let ctx = ParseContext::new(Arc::new(
             terms
@@ -156,32 +394,193 @@ mod tests {
         ctx.term(token::ParsedSpan::new(input))
     }
 
+    #[test_case("\"穂積しょう\""=>Some(("hozumi".into(), "穂積しょう".into()));"body")]
+    #[test_case("\"しょう\""=>Some(("hozumi".into(), "しょう".into()));"alias")]
+    #[test_case("\"ｼｮｳ\""=>Some(("hozumi".into(), "ｼｮｳ".into()));"half_width_alias")]
+    #[test_case("\"葛\u{e0100}城\""=>Some(("katsuragi".into(), "葛\u{e0100}城".into()));"variation_selector")]
+    #[test_case("\"ＫＡＴＳＵＲＡＧＩ\""=>Some(("katsuragi".into(), "ＫＡＴＳＵＲＡＧＩ".into()));"full_width_alias")]
+    #[test_case("\"穂積\""=>None;"unknown")]
+    fn context_term_alias_works(input: &str) -> Option<(String, String)> {
+        let terms = vec![
+            new_sample_term("hozumi", "穂積しょう")
+                .with_aliases(vec!["しょう".into(), "ショウ".into()]),
+            new_sample_term("katsuragi", "葛城").with_aliases(vec!["KATSURAGI".into()]),
+        ];
+        let ctx = ParseContext::new(Arc::new(
+            terms
+                .into_iter()
+                .map(|term| (term.body().clone(), term))
+                .collect(),
+        ));
+        match ctx.term(token::ParsedSpan::new(input)) {
+            Ok((_, ParsedToken::Term { body, term_id, .. })) => {
+                Some((term_id.value().clone(), body.fragment().to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    #[test_case(vec![("hozumi", "穂積"), ("hozumi_shou", "穂積しょう")], "穂積しょうと穂積"=>vec![
+        (0, 15, "hozumi_shou".into()),
+        (18, 6, "hozumi".into()),
+    ];"leftmost_longest")]
+    #[test_case(vec![("tokyo", "東京"), ("tokyo_to", "東京都")], "東京都庁と東京"=>vec![
+        (15, 6, "tokyo".into()),
+    ];"kanji_compound")]
+    #[test_case(vec![("glass", "ガラス"), ("glass_ball", "ガラス玉")], "ガラス玉座"=>vec![
+        (0, 9, "glass".into()),
+    ];"shorter_outside_compound")]
+    #[test_case(vec![("shou", "ショウ")], "ｼｮｳとショウ"=>vec![
+        (0, 9, "shou".into()),
+        (12, 9, "shou".into()),
+    ];"normalized")]
+    fn term_matcher_find_iter_works(
+        terms: Vec<(&str, &str)>,
+        input: &str,
+    ) -> Vec<(usize, usize, String)> {
+        let matcher = TermMatcher::new(
+            terms
+                .into_iter()
+                .map(|(id, body)| new_sample_term(id, body))
+                .collect(),
+        );
+        matcher
+            .find_iter(input)
+            .into_iter()
+            .map(|(offset, len, term)| (offset, len, term.id().value().clone()))
+            .collect()
+    }
+
+    #[test]
+    fn term_matcher_large_glossary_works() {
+        let matcher = TermMatcher::new(
+            (0..5000)
+                .map(|i| new_sample_term(&format!("term{}", i), &format!("用語{}号", i)))
+                .collect(),
+        );
+        let found = matcher.find_iter("まず用語4999号、次に用語12号。");
+        let found: Vec<_> = found
+            .into_iter()
+            .map(|(offset, len, term)| (offset, len, term.id().value().as_str()))
+            .collect();
+        assert_eq!(found, vec![(6, 13, "term4999"), (28, 11, "term12")]);
+    }
+
     fn default_ctx() -> ParseContext {
         ParseContext::new(Arc::new(BTreeMap::new()))
     }
 
     #[test_case("|漢字$かんじ$"=> Ok((token::test_helper::new_test_result_span(18, 1, ""),
     ParsedToken::Annotation{
-        body: token::test_helper::new_test_result_span(1, 1, "漢字"),
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
+        body: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(1, 1, "漢字")),
         description: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(8, 1, "かんじ")),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(7, 1, "$"),token::test_helper::new_test_result_span(17, 1, "$")),
     }));"half_all")]
     #[test_case("|漢字(かんじ)$せつめい$"=> Ok((token::test_helper::new_test_result_span(32, 1, ""),
     ParsedToken::Annotation{
-        body: token::test_helper::new_test_result_span(1, 1, "漢字(かんじ)"),
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
+        body: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(1, 1, "漢字(かんじ)")),
         description: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(19, 1, "せつめい")),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(18, 1, "$"),token::test_helper::new_test_result_span(31, 1, "$")),
     }));"with_ruby")]
     #[test_case("||漢字ふ(かんじ)$せつめい$"=> Ok((token::test_helper::new_test_result_span(36, 1, ""),
     ParsedToken::Annotation{
-        body: token::test_helper::new_test_result_span(1, 1, "|漢字ふ(かんじ)"),
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
+        body: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(1, 1, "|漢字ふ(かんじ)")),
         description: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(23, 1, "せつめい")),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(22, 1, "$"),token::test_helper::new_test_result_span(35, 1, "$")),
     }));"with_ruby_directive")]
     #[test_case("|漢字＄かんじ$"=> Ok((token::test_helper::new_test_result_span(20, 1, ""),
     ParsedToken::Annotation{
-        body: token::test_helper::new_test_result_span(1, 1, "漢字"),
+        directive: token::test_helper::new_test_result_span(0, 1, "|"),
+        body: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(1, 1, "漢字")),
         description: iterator::TextIterator::new(default_ctx(),token::test_helper::new_test_result_span(10, 1, "かんじ")),
+        delimiter: ParsedDelimiter::new(token::test_helper::new_test_result_span(7, 1, "＄"),token::test_helper::new_test_result_span(19, 1, "$")),
     }));"wide_start")]
     #[test_case("|$hoge$"=> Err(new_error(token::test_helper::new_test_result_span(1, 1, "$hoge$"),nom::error::ErrorKind::TakeWhile1)))]
     fn directive_annotation_works(input: &str) -> IResult {
         default_ctx().directive_annotation(token::ParsedSpan::new(input))
     }
+
+    #[test_case("|漢字(かんじ)$せつめい$"=>TokenText::new(vec![
+        Token::new_kanji_ruby(Span::new("漢字".into(),Position::new(1,1).with_columns(2,2,2)),Span::new("かんじ".into(),Position::new(1,8).with_columns(5,5,5)),Delimiter::new("(".into(),")".into())),
+    ]);"kanji_ruby")]
+    #[test_case("||漢字ふ(かんじ)$せつめい$"=>TokenText::new(vec![
+        Token::new_ruby("|".into(),Span::new("漢字ふ".into(),Position::new(1,2).with_columns(3,3,3)),Span::new("かんじ".into(),Position::new(1,12).with_columns(7,7,7)),Delimiter::new("(".into(),")".into())),
+    ]);"directive_ruby")]
+    #[test_case("|《《強調》》$せつめい$"=>TokenText::new(vec![
+        Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("強調".into(),Position::new(1,7).with_columns(4,4,4)))]),EmphasisStyle::Sesame,Delimiter::new("《《".into(),"》》".into())),
+    ]);"emphasis_mark")]
+    fn directive_annotation_body_works(input: &str) -> TokenText {
+        match default_ctx().directive_annotation(token::ParsedSpan::new(input)) {
+            Ok((_, ParsedToken::Annotation { body, .. })) => body.collect(),
+            _ => TokenText::new(vec![]),
+        }
+    }
+
+    fn delimiter(start: &'static str, end: &'static str) -> Delimiter<'static> {
+        Delimiter::new(start.into(), end.into())
+    }
+
+    #[test_case(ParserOptions::default().with_kanji_ruby(false), "漢字(かんじ)"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("漢字(かんじ)".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"kanji_ruby_disabled")]
+    #[test_case(ParserOptions::default().with_ruby_brackets(vec![BracketPair::new('《', '》')]), "漢字(かんじ)|本《ほん》"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("漢字(かんじ)".into(),Position::new(1,0).with_columns(1,1,1))),
+        Token::new_ruby("|".into(),Span::new("本".into(),Position::new(1,18).with_columns(9,9,9)),Span::new("ほん".into(),Position::new(1,24).with_columns(11,11,11)),delimiter("《","》")),
+    ]);"ruby_brackets")]
+    #[test_case(ParserOptions::default().with_max_ruby_count_body(1), "漢字(かんじ)"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("漢字(かんじ)".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"max_ruby_count_body")]
+    #[test_case(ParserOptions::default().with_max_ruby_count_per_body_char(1), "|漢字(かんじ)"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("|漢字(かんじ)".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"max_ruby_count_per_body_char")]
+    #[test_case(ParserOptions::default().with_directive_ruby(false), "|ほげ(ふが)"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("|ほげ(ふが)".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"directive_ruby_disabled")]
+    #[test_case(ParserOptions::default().with_emphasis_mark(false), "《《傍点》》"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("《《傍点》》".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"emphasis_mark_disabled")]
+    #[test_case(ParserOptions::default().with_emphasis_mark_brackets(vec![BracketPair::new('⟪', '⟫')]), "⟪⟪傍点⟫⟫"=>TokenText::new(vec![
+        Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("傍点".into(),Position::new(1,6).with_columns(3,3,3)))]),EmphasisStyle::Sesame,delimiter("⟪⟪","⟫⟫")),
+    ]);"emphasis_mark_brackets")]
+    #[test_case(ParserOptions::default().with_annotation(false), "|ほげ$ふが$"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("|ほげ$ふが$".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"annotation_disabled")]
+    #[test_case(ParserOptions::default().with_term(false), "\"無\""=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("\"無\"".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"term_disabled")]
+    #[test_case(ParserOptions::default().with_directives(vec!['#']), "#ほげ(ふが)|本"=>TokenText::new(vec![
+        Token::new_ruby("#".into(),Span::new("ほげ".into(),Position::new(1,1).with_columns(2,2,2)),Span::new("ふが".into(),Position::new(1,8).with_columns(5,5,5)),delimiter("(",")")),
+        Token::new_plaintext(Span::new("|本".into(),Position::new(1,15).with_columns(8,8,8))),
+    ]);"directives")]
+    #[test_case(ParserOptions::default().with_term_brackets(vec![BracketPair::new('«', '»')]), "«無»\"無\""=>TokenText::new(vec![
+        Token::new_term(Span::new("無".into(),Position::new(1,2).with_columns(2,2,2)),Id::new("term_id1"),delimiter("«","»")),
+        Token::new_plaintext(Span::new("\"無\"".into(),Position::new(1,7).with_columns(4,4,4))),
+    ]);"term_brackets")]
+    #[test_case(ParserOptions::default().with_annotation_brackets(vec![BracketPair::new('[', ']')]), "|ほげ[ふが]"=>TokenText::new(vec![
+        Token::new_annotation("|".into(),TokenText::new(vec![
+            Token::new_plaintext(Span::new("ほげ".into(),Position::new(1,1).with_columns(2,2,2))),
+        ]),TokenText::new(vec![
+            Token::new_plaintext(Span::new("ふが".into(),Position::new(1,8).with_columns(5,5,5))),
+        ]),delimiter("[","]")),
+    ]);"annotation_brackets")]
+    fn context_options_works(options: ParserOptions, input: &str) -> TokenText {
+        let ctx = ParseContext::new(Arc::new(
+            vec![new_sample_term("term_id1", "無")]
+                .into_iter()
+                .map(|term| (term.body().clone(), term))
+                .collect(),
+        ))
+        .with_options(options);
+        iterator::TextIterator::new(ctx, token::ParsedSpan::new(input)).collect()
+    }
+
+    #[test_case(default_ctx().with_dialect(Arc::new(dialect::Kakuyomu))=>dialect::Dialect::options(&dialect::Kakuyomu);"dialect")]
+    #[test_case(default_ctx().with_options(ParserOptions::default().with_directives(vec!['#'])).with_dialect(Arc::new(dialect::Kakuyomu))=>ParserOptions::default().with_directives(vec!['#']);"options_before_dialect")]
+    #[test_case(default_ctx().with_dialect(Arc::new(dialect::Kakuyomu)).with_options(ParserOptions::default().with_directives(vec!['#']))=>ParserOptions::default().with_directives(vec!['#']);"options_after_dialect")]
+    fn context_dialect_options_works(ctx: ParseContext) -> ParserOptions {
+        ctx.options().clone()
+    }
 }
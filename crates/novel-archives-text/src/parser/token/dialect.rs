@@ -0,0 +1,393 @@
+use super::*;
+use nom::branch::alt;
+use nom::InputTake;
+use nom_extend::character;
+use std::fmt::Debug;
+use token::complete as token_complete;
+
+pub trait Dialect: Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn token<'a>(&self, context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a>;
+
+    fn options(&self) -> ParserOptions {
+        ParserOptions::default()
+    }
+
+    fn back_reference_target<'b>(&self, _token: &'b ParsedToken) -> Option<&'b str> {
+        None
+    }
+
+    fn resolve_back_reference<'a>(
+        &self,
+        token: ParsedToken<'a>,
+        _target: ParsedSpan<'a>,
+    ) -> ParsedToken<'a> {
+        token
+    }
+
+    fn unresolved_back_reference<'a>(
+        &self,
+        _context: &ParseContext,
+        token: ParsedToken<'a>,
+    ) -> ParsedToken<'a> {
+        token
+    }
+}
+
+impl PartialEq for dyn Dialect {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+pub fn from_name(name: &str) -> Option<Arc<dyn Dialect>> {
+    match name {
+        "novel-archives" => Some(Arc::new(NovelArchives)),
+        "kakuyomu" => Some(Arc::new(Kakuyomu)),
+        "narou" => Some(Arc::new(Narou)),
+        "aozora" => Some(Arc::new(Aozora)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct NovelArchives;
+
+impl Dialect for NovelArchives {
+    fn name(&self) -> &'static str {
+        "novel-archives"
+    }
+
+    fn token<'a>(&self, context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+        let c = match input.fragment().chars().next() {
+            Some(c) => c,
+            None => return Err(new_error(input, nom::error::ErrorKind::Eof)),
+        };
+        match Lookahead::of(c, context.options()) {
+            Lookahead::Directive => alt((
+                |input| token_complete::escape_with(input, context),
+                |input| context.directive_annotation(input),
+                |input| token_complete::directive_ruby_with(input, context),
+                |input| token_complete::directive_other(input, context),
+            ))(input),
+            Lookahead::Term => alt((
+                |input| context.term(input),
+                |input| token_complete::term_directive_other(input, context),
+            ))(input),
+            Lookahead::Kanji => alt((
+                |input| token_complete::plaintext_with(input, context),
+                |input| token_complete::kanji_ruby_with(input, context),
+            ))(input),
+            Lookahead::EmphasisMark => alt((
+                |input| token_complete::emphasis_mark_with(input, context),
+                |input| token_complete::emphasis_mark_start_other(input, context),
+                |input| token_complete::plaintext_with(input, context),
+            ))(input),
+            Lookahead::Space => token_complete::space(input),
+            Lookahead::NewLine => token_complete::newline(input),
+            Lookahead::Plaintext => token_complete::plaintext_with(input, context),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Lookahead {
+    Directive,
+    Term,
+    Kanji,
+    EmphasisMark,
+    Space,
+    NewLine,
+    Plaintext,
+}
+
+impl Lookahead {
+    fn of(c: char, options: &ParserOptions) -> Self {
+        if options.is_start_directive(c) {
+            Lookahead::Directive
+        } else if options.is_start_term(c) {
+            Lookahead::Term
+        } else if character::is_kanji_related(c) {
+            Lookahead::Kanji
+        } else if options.is_start_emphasis_mark(c) {
+            Lookahead::EmphasisMark
+        } else if character::is_any_space(c) {
+            Lookahead::Space
+        } else if character::is_any_newline(c) {
+            Lookahead::NewLine
+        } else {
+            Lookahead::Plaintext
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Kakuyomu;
+
+impl Dialect for Kakuyomu {
+    fn name(&self) -> &'static str {
+        "kakuyomu"
+    }
+
+    fn token<'a>(&self, context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+        NovelArchives.token(context, input)
+    }
+
+    fn options(&self) -> ParserOptions {
+        ParserOptions::default()
+            .with_ruby_brackets(vec![BracketPair::new('《', '》')])
+            .with_annotation(false)
+            .with_term(false)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Narou;
+
+impl Dialect for Narou {
+    fn name(&self) -> &'static str {
+        "narou"
+    }
+
+    fn token<'a>(&self, context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+        match NovelArchives.token(context, input)? {
+            (
+                _,
+                ParsedToken::KanjiRuby {
+                    body,
+                    ruby,
+                    delimiter,
+                },
+            ) if !is_narou_kanji_ruby(&ruby, &delimiter) => {
+                let (input, body) = input.take_split(body.fragment().len());
+                Ok((input, ParsedToken::Plaintext(body)))
+            }
+            (
+                _,
+                ParsedToken::MonoRuby {
+                    body,
+                    pairs,
+                    delimiter,
+                    ..
+                },
+            ) if !pairs
+                .iter()
+                .all(|pair| is_narou_kanji_ruby(pair.ruby(), &delimiter)) =>
+            {
+                let (input, body) = input.take_split(body.fragment().len());
+                Ok((input, ParsedToken::Plaintext(body)))
+            }
+            result => Ok(result),
+        }
+    }
+
+    fn options(&self) -> ParserOptions {
+        ParserOptions::default()
+            .with_emphasis_mark(false)
+            .with_annotation(false)
+            .with_term(false)
+    }
+}
+
+fn is_narou_kanji_ruby(ruby: &ParsedSpan, delimiter: &ParsedDelimiter) -> bool {
+    !delimiter
+        .start()
+        .fragment()
+        .starts_with(character::is_start_parenthesis_ruby)
+        || ruby
+            .fragment()
+            .chars()
+            .all(|c| character::is_hiragana(c) || character::is_katakana(c))
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Aozora;
+
+impl Dialect for Aozora {
+    fn name(&self) -> &'static str {
+        "aozora"
+    }
+
+    fn token<'a>(&self, context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+        let c = match input.fragment().chars().next() {
+            Some(c) => c,
+            None => return Err(new_error(input, nom::error::ErrorKind::Eof)),
+        };
+        match AozoraLookahead::of(c) {
+            AozoraLookahead::Directive => alt((aozora::ruby, aozora::other))(input),
+            AozoraLookahead::Gaiji => {
+                alt((|input| aozora::gaiji(context, input), aozora::other))(input)
+            }
+            AozoraLookahead::Note => alt((
+                |input| aozora::emphasis_mark(context, input),
+                |input| aozora::note(context, input),
+                aozora::other,
+            ))(input),
+            AozoraLookahead::Kanji => aozora::kanji_ruby(input),
+            AozoraLookahead::RubyStart => aozora::other(input),
+            AozoraLookahead::Space => token_complete::space(input),
+            AozoraLookahead::NewLine => token_complete::newline(input),
+            AozoraLookahead::Plaintext => aozora::plaintext(input),
+        }
+    }
+
+    fn back_reference_target<'b>(&self, token: &'b ParsedToken) -> Option<&'b str> {
+        aozora::back_reference_target(token)
+    }
+
+    fn resolve_back_reference<'a>(
+        &self,
+        token: ParsedToken<'a>,
+        target: ParsedSpan<'a>,
+    ) -> ParsedToken<'a> {
+        aozora::resolve_back_reference(token, target)
+    }
+
+    fn unresolved_back_reference<'a>(
+        &self,
+        context: &ParseContext,
+        token: ParsedToken<'a>,
+    ) -> ParsedToken<'a> {
+        aozora::unresolved_back_reference(context, token)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum AozoraLookahead {
+    Directive,
+    Gaiji,
+    Note,
+    Kanji,
+    RubyStart,
+    Space,
+    NewLine,
+    Plaintext,
+}
+
+impl AozoraLookahead {
+    fn of(c: char) -> Self {
+        if character::is_start_directive(c) {
+            AozoraLookahead::Directive
+        } else if character::is_gaiji_mark(c) {
+            AozoraLookahead::Gaiji
+        } else if character::is_start_aozora_note(c) {
+            AozoraLookahead::Note
+        } else if character::is_kanji_related(c) {
+            AozoraLookahead::Kanji
+        } else if character::is_start_aozora_ruby(c) {
+            AozoraLookahead::RubyStart
+        } else if character::is_any_space(c) {
+            AozoraLookahead::Space
+        } else if character::is_any_newline(c) {
+            AozoraLookahead::NewLine
+        } else {
+            AozoraLookahead::Plaintext
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use test_case::test_case;
+
+    fn parse<'a>(name: &str, input: &'a str) -> TokenText<'a> {
+        iterator::TextIterator::new(
+            ParseContext::new(Arc::new(BTreeMap::new())).with_dialect(from_name(name).unwrap()),
+            token::ParsedSpan::new(input),
+        )
+        .collect()
+    }
+
+    fn delimiter(start: &'static str, end: &'static str) -> Delimiter<'static> {
+        Delimiter::new(start.into(), end.into())
+    }
+
+    #[test_case("novel-archives", "漢字(かんじ)"=>TokenText::new(vec![
+        Token::new_kanji_ruby(Span::new("漢字".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("かんじ".into(),Position::new(1,7).with_columns(4,4,4)),delimiter("(",")")),
+    ]);"novel_archives_parenthesis")]
+    #[test_case("kakuyomu", "漢字(かんじ)"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("漢字(かんじ)".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"kakuyomu_parenthesis")]
+    #[test_case("kakuyomu", "漢字《かんじ》と《《傍点》》"=>TokenText::new(vec![
+        Token::new_kanji_ruby(Span::new("漢字".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("かんじ".into(),Position::new(1,9).with_columns(4,4,4)),delimiter("《","》")),
+        Token::new_plaintext(Span::new("と".into(),Position::new(1,21).with_columns(8,8,8))),
+        Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("傍点".into(),Position::new(1,30).with_columns(11,11,11)))]),EmphasisStyle::Sesame,delimiter("《《","》》")),
+    ]);"kakuyomu_angle")]
+    #[test_case("narou", "漢字(かんじ)"=>TokenText::new(vec![
+        Token::new_kanji_ruby(Span::new("漢字".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("かんじ".into(),Position::new(1,7).with_columns(4,4,4)),delimiter("(",")")),
+    ]);"narou_parenthesis_kana")]
+    #[test_case("narou", "漢字(注記)"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("漢字(注記)".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"narou_parenthesis_not_kana")]
+    #[test_case("narou", "漢字(かん・じ)"=>TokenText::new(vec![
+        Token::new_mono_ruby(Span::new("漢字".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("かん・じ".into(),Position::new(1,7).with_columns(4,4,4)),vec![
+            RubyPair::new(Span::new("漢".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("かん".into(),Position::new(1,7).with_columns(4,4,4))),
+            RubyPair::new(Span::new("字".into(),Position::new(1,3).with_columns(2,2,2)),Span::new("じ".into(),Position::new(1,16).with_columns(7,7,7))),
+        ],delimiter("(",")")),
+    ]);"narou_mono_ruby_kana")]
+    #[test_case("narou", "漢字(注・記)"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("漢字(注・記)".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"narou_mono_ruby_not_kana")]
+    #[test_case("narou", "《《傍点》》"=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("《《傍点》》".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"narou_emphasis_mark")]
+    #[test_case("novel-archives", "|\"ほげ\""=>TokenText::new(vec![
+        Token::new_escape("|".into(),Span::new("\"".into(),Position::new(1,1).with_columns(2,2,2))),
+        Token::new_plaintext(Span::new("ほげ\"".into(),Position::new(1,2).with_columns(3,3,3))),
+    ]);"novel_archives_escape_term")]
+    #[test_case("kakuyomu", "|\"ほげ\""=>TokenText::new(vec![
+        Token::new_plaintext(Span::new("|\"ほげ\"".into(),Position::new(1,0).with_columns(1,1,1))),
+    ]);"kakuyomu_escape_term")]
+    fn dialect_token_works<'a>(name: &str, input: &'a str) -> TokenText<'a> {
+        parse(name, input)
+    }
+
+    #[test_case('｜'=>Lookahead::Directive)]
+    #[test_case('”'=>Lookahead::Term)]
+    #[test_case('々'=>Lookahead::Kanji)]
+    #[test_case('⟪'=>Lookahead::Plaintext;"removed_emphasis_mark")]
+    #[test_case('〔'=>Lookahead::EmphasisMark;"custom_emphasis_mark")]
+    #[test_case('　'=>Lookahead::Space)]
+    #[test_case('\r'=>Lookahead::NewLine)]
+    #[test_case('(' =>Lookahead::Plaintext)]
+    fn lookahead_of_works(c: char) -> Lookahead {
+        let options = ParserOptions::default().with_emphasis_mark_brackets(vec![
+            BracketPair::new('《', '》'),
+            BracketPair::new('〔', '〕'),
+        ]);
+        Lookahead::of(c, &options)
+    }
+
+    #[test_case('#'=>Lookahead::Directive)]
+    #[test_case('｜'=>Lookahead::Plaintext;"removed_directive")]
+    #[test_case('«'=>Lookahead::Term)]
+    #[test_case('"'=>Lookahead::Plaintext;"removed_term")]
+    fn lookahead_of_custom_delimiters_works(c: char) -> Lookahead {
+        let options = ParserOptions::default()
+            .with_directives(vec!['#'])
+            .with_term_brackets(vec![BracketPair::new('«', '»')]);
+        Lookahead::of(c, &options)
+    }
+
+    #[test_case('｜'=>AozoraLookahead::Directive)]
+    #[test_case('※'=>AozoraLookahead::Gaiji)]
+    #[test_case('［'=>AozoraLookahead::Note)]
+    #[test_case('々'=>AozoraLookahead::Kanji)]
+    #[test_case('《'=>AozoraLookahead::RubyStart)]
+    #[test_case('　'=>AozoraLookahead::Space)]
+    #[test_case('\n'=>AozoraLookahead::NewLine)]
+    #[test_case('"'=>AozoraLookahead::Plaintext)]
+    fn aozora_lookahead_of_works(c: char) -> AozoraLookahead {
+        AozoraLookahead::of(c)
+    }
+
+    #[test_case("novel-archives"=>Some("novel-archives"))]
+    #[test_case("aozora"=>Some("aozora"))]
+    #[test_case("unknown"=>None)]
+    fn from_name_works(name: &str) -> Option<&'static str> {
+        from_name(name).map(|dialect| dialect.name())
+    }
+}
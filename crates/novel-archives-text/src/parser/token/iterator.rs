@@ -1,65 +1,98 @@
 use super::*;
 use nom::InputTake;
-use std::{
-    iter::FromIterator,
-    ops::{Deref, DerefMut},
-};
+use std::collections::VecDeque;
+use std::iter::FromIterator;
 
 #[derive(new, Debug, PartialEq, Clone)]
 pub struct TextIterator<'a> {
     context: ParseContext,
     input: ParsedSpan<'a>,
     #[new(default)]
-    next_token: Box<Option<ParsedToken<'a>>>,
+    next_tokens: VecDeque<ParsedToken<'a>>,
+}
+
+impl<'a> TextIterator<'a> {
+    pub fn input(&self) -> &ParsedSpan<'a> {
+        &self.input
+    }
+
+    pub fn context(&self) -> &ParseContext {
+        &self.context
+    }
+
+    fn push_plaintext(&mut self, span: ParsedSpan<'a>) {
+        let mut span = span;
+        let mut consumed = 0;
+        for (offset, len, term) in self.context.find_unquoted_terms(span.fragment()) {
+            let (rest, parsed) = span.take_split(offset - consumed);
+            if !parsed.fragment().is_empty() {
+                self.next_tokens.push_back(ParsedToken::Plaintext(parsed));
+            }
+            let (rest, body) = rest.take_split(len);
+            self.next_tokens.push_back(ParsedToken::Term {
+                body,
+                term_id: term.id().clone(),
+                delimiter: ParsedDelimiter::new(body.take(0), rest.take(0)),
+            });
+            span = rest;
+            consumed = offset + len;
+        }
+        if !span.fragment().is_empty() {
+            self.next_tokens.push_back(ParsedToken::Plaintext(span));
+        }
+    }
 }
 
 impl<'a> Iterator for TextIterator<'a> {
     type Item = ParsedToken<'a>;
     fn next(&mut self) -> std::option::Option<<Self as std::iter::Iterator>::Item> {
-        if let Some(token) = self.next_token.deref() {
-            let token = token.clone();
-            *self.next_token.deref_mut() = None;
-            Some(token)
-        } else {
-            let (input, parsed) = self.context.token(self.input).ok()?;
-            match parsed {
-                ParsedToken::Plaintext(span) => {
-                    let mut input = input;
-                    let mut len = span.fragment().len();
-                    loop {
-                        match self.context.token(input) {
-                            Ok((new_input, token)) => match token {
-                                ParsedToken::Plaintext(span) => {
-                                    input = new_input;
-                                    len += span.fragment().len();
-                                }
-                                _ => {
-                                    let (_, parsed) = self.input.take_split(len);
-                                    *self.next_token.deref_mut() = Some(token);
-                                    self.input = new_input;
-                                    return Some(ParsedToken::Plaintext(parsed));
-                                }
-                            },
-                            Err(_) => {
-                                let (input, parsed) = self.input.take_split(len);
-                                self.input = input;
-                                return Some(ParsedToken::Plaintext(parsed));
-                            }
-                        }
-                    }
-                }
-                _ => {
-                    self.input = input;
-                    Some(parsed)
+        if let Some(token) = self.next_tokens.pop_front() {
+            return Some(token);
+        }
+        let mut input = self.input;
+        let mut len = 0;
+        let next_token = loop {
+            match self.context.token(input) {
+                Ok((new_input, ParsedToken::Plaintext(span))) => {
+                    input = new_input;
+                    len += span.fragment().len();
                 }
+                Ok((new_input, token)) => break Some((new_input, token)),
+                Err(_) => match input.fragment().chars().next() {
+                    Some(c) => {
+                        input = input.take_split(c.len_utf8()).0;
+                        len += c.len_utf8();
+                    }
+                    None => break None,
+                },
             }
+        };
+        if len == 0 && next_token.is_none() {
+            return None;
         }
+        let (input, mut parsed) = self.input.take_split(len);
+        self.input = input;
+        let next_token = next_token.map(|(new_input, token)| {
+            self.input = new_input;
+            let dialect = self.context.dialect();
+            let target_len = match dialect.back_reference_target(&token) {
+                Some(target) if parsed.fragment().ends_with(target) => target.len(),
+                Some(_) => return dialect.unresolved_back_reference(&self.context, token),
+                None => return token,
+            };
+            let (target, prefix) = parsed.take_split(len - target_len);
+            parsed = prefix;
+            dialect.resolve_back_reference(token, target)
+        });
+        self.push_plaintext(parsed);
+        self.next_tokens.extend(next_token);
+        self.next_tokens.pop_front()
     }
 }
 
-impl<'a> FromIterator<ParsedToken<'a>> for TokenText {
+impl<'a> FromIterator<ParsedToken<'a>> for TokenText<'a> {
     fn from_iter<I: IntoIterator<Item = ParsedToken<'a>>>(iter: I) -> Self {
-        TokenText::new(iter.into_iter().map(|token| token.into()).collect())
+        PositionCursor::default().text(iter)
     }
 }
 
@@ -93,115 +126,202 @@ mod tests {
 
     #[test_case(token_works_testdata::hit_terms(),"穂積しょう" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("穂積しょう".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("穂積しょう".into(),Position::new(1,0).with_columns(1,1,1))),
             ],
             );"not_quote_term")]
     #[test_case(token_works_testdata::hit_terms(),"\"穂積しょう\"" => TokenText::new(
             vec![
-                Token::new_term(Span::new("穂積しょう".into(),Position::new(1,1)),Id::new("term_id1")),
+                Token::new_term(Span::new("穂積しょう".into(),Position::new(1,1).with_columns(2,2,2)),Id::new("term_id1"),Delimiter::new("\"".into(),"\"".into())),
             ],
             );"quote_term")]
     #[test_case(token_works_testdata::hit_terms(),"穂積しょうたろう" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("穂積しょうたろう".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("穂積しょうたろう".into(),Position::new(1,0).with_columns(1,1,1))),
             ]
             );"not_quote_after_sentence")]
     #[test_case(token_works_testdata::hit_terms(),"\"穂積しょう\"たろう" => TokenText::new(
             vec![
-                Token::new_term(Span::new("穂積しょう".into(),Position::new(1,1)),Id::new("term_id1")),
-                Token::new_plaintext(Span::new("たろう".into(),Position::new(1,17))),
+                Token::new_term(Span::new("穂積しょう".into(),Position::new(1,1).with_columns(2,2,2)),Id::new("term_id1"),Delimiter::new("\"".into(),"\"".into())),
+                Token::new_plaintext(Span::new("たろう".into(),Position::new(1,17).with_columns(8,8,8))),
             ],
             );"quote_after_sentence")]
     #[test_case(token_works_testdata::other_terms(),"\"穂積しょう\"たろう" =>TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("\"穂積しょう\"たろう".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("\"穂積しょう\"たろう".into(),Position::new(1,0).with_columns(1,1,1))),
             ],
             ) )]
     #[test_case(token_works_testdata::hit_terms(),"|穂積《ほづみ》しょうたろう" => TokenText::new(
             vec![
-                Token::new_ruby(Span::new("穂積".into(),Position::new(1,1)),Span::new("ほづみ".into(),Position::new(1,10))),
-                Token::new_plaintext(Span::new("しょうたろう".into(),Position::new(1,22))),
+                Token::new_ruby("|".into(),Span::new("穂積".into(),Position::new(1,1).with_columns(2,2,2)),Span::new("ほづみ".into(),Position::new(1,10).with_columns(5,5,5)),Delimiter::new("《".into(),"》".into())),
+                Token::new_plaintext(Span::new("しょうたろう".into(),Position::new(1,22).with_columns(9,9,9))),
             ],
             ))]
     #[test_case(token_works_testdata::hit_terms(),"穂積《ほづみ》しょうたろう" => TokenText::new(
             vec![
-                Token::new_kanji_ruby(Span::new("穂積".into(),Position::new(1,0)),Span::new("ほづみ".into(),Position::new(1,9))),
-                Token::new_plaintext(Span::new("しょうたろう".into(),Position::new(1,21))),
+                Token::new_kanji_ruby(Span::new("穂積".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("ほづみ".into(),Position::new(1,9).with_columns(4,4,4)),Delimiter::new("《".into(),"》".into())),
+                Token::new_plaintext(Span::new("しょうたろう".into(),Position::new(1,21).with_columns(8,8,8))),
             ],
             );"kanji_ruby1")]
     #[test_case(token_works_testdata::hit_terms(),"穂積(ほづみ)しょうたろう" => TokenText::new(
             vec![
-                Token::new_kanji_ruby(Span::new("穂積".into(),Position::new(1,0)),Span::new("ほづみ".into(),Position::new(1,7))),
-                Token::new_plaintext(Span::new("しょうたろう".into(),Position::new(1,17))),
+                Token::new_kanji_ruby(Span::new("穂積".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("ほづみ".into(),Position::new(1,7).with_columns(4,4,4)),Delimiter::new("(".into(),")".into())),
+                Token::new_plaintext(Span::new("しょうたろう".into(),Position::new(1,17).with_columns(8,8,8))),
             ],
             );"kanji_ruby2")]
     #[test_case(token_works_testdata::hit_terms(),"|穂積しょうたろう" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("|穂積しょうたろう".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("|穂積しょうたろう".into(),Position::new(1,0).with_columns(1,1,1))),
             ],
             ))]
     #[test_case(token_works_testdata::other_terms(),"  スペース確認" => TokenText::new(
             vec![
-                Token::new_spase(Span::new("  ".into(),Position::new(1,0))),
-                Token::new_plaintext(Span::new("スペース確認".into(),Position::new(1,2))),
+                Token::new_spase(Span::new("  ".into(),Position::new(1,0).with_columns(1,1,1))),
+                Token::new_plaintext(Span::new("スペース確認".into(),Position::new(1,2).with_columns(3,3,3))),
             ],
             ))]
     #[test_case(token_works_testdata::hit_terms(),"\"穂積しょう" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("\"穂積しょう".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("\"穂積しょう".into(),Position::new(1,0).with_columns(1,1,1))),
             ],
             );"part_quote_term")]
     #[test_case(token_works_testdata::hit_terms(),"穂積しょう\n\"穂積しょう\"" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("穂積しょう".into(),Position::new(1,0))),
-                Token::new_new_line(Span::new("\n".into(),Position::new(1,15))),
-                Token::new_term(Span::new("穂積しょう".into(),Position::new(2,17)),Id::new("term_id1")),
+                Token::new_plaintext(Span::new("穂積しょう".into(),Position::new(1,0).with_columns(1,1,1))),
+                Token::new_new_line(Span::new("\n".into(),Position::new(1,15).with_columns(6,6,6))),
+                Token::new_term(Span::new("穂積しょう".into(),Position::new(2,17).with_columns(2,2,2)),Id::new("term_id1"),Delimiter::new("\"".into(),"\"".into())),
             ],
             );"new_line_with_term")]
     #[test_case(token_works_testdata::other_terms(),"《《傍点確認》》" => TokenText::new(
             vec![
-                Token::new_emphasis_mark(Span::new("傍点確認".into(),Position::new(1,6))),
+                Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("傍点確認".into(),Position::new(1,6).with_columns(3,3,3)))]),EmphasisStyle::Sesame,Delimiter::new("《《".into(),"》》".into())),
             ],
             ))]
     #[test_case(token_works_testdata::other_terms(),"《《《other傍点確認》》" => TokenText::new(
             vec![
-                Token::new_emphasis_mark(Span::new("《other傍点確認".into(),Position::new(1,6))),
+                Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("《other傍点確認".into(),Position::new(1,6).with_columns(3,3,3)))]),EmphasisStyle::Sesame,Delimiter::new("《《".into(),"》》".into())),
             ],
             ))]
     #[test_case(token_works_testdata::other_terms(),"《《not傍点確認》" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("《《not傍点確認》".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("《《not傍点確認》".into(),Position::new(1,0).with_columns(1,1,1))),
             ],
             ))]
     #[test_case(token_works_testdata::other_terms(),"《not傍点》" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("《not傍点》".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("《not傍点》".into(),Position::new(1,0).with_columns(1,1,1))),
             ],
             );"not_emphasis_1")]
     #[test_case(token_works_testdata::other_terms(),"《》《not傍点》は" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("《》《not傍点》は".into(),Position::new(1,0))),
+                Token::new_plaintext(Span::new("《》《not傍点》は".into(),Position::new(1,0).with_columns(1,1,1))),
             ],
             );"not_emphasis_2")]
     #[test_case(token_works_testdata::other_terms(),"《》：ルビ\n（例）私《わたくし》は" => TokenText::new(
             vec![
-                Token::new_plaintext(Span::new("《》：ルビ".into(),Position::new(1,0))),
-                Token::new_new_line(Span::new("\n".into(),Position::new(1,15))),
-                Token::new_plaintext(Span::new("（例）".into(),Position::new(2,16))),
-                Token::new_kanji_ruby(Span::new("私".into(),Position::new(2,25)),Span::new("わたくし".into(),Position::new(2,31))),
-                Token::new_plaintext(Span::new("は".into(),Position::new(2,46))),
+                Token::new_plaintext(Span::new("《》：ルビ".into(),Position::new(1,0).with_columns(1,1,1))),
+                Token::new_new_line(Span::new("\n".into(),Position::new(1,15).with_columns(6,6,6))),
+                Token::new_plaintext(Span::new("（例）".into(),Position::new(2,16).with_columns(1,1,1))),
+                Token::new_kanji_ruby(Span::new("私".into(),Position::new(2,25).with_columns(4,4,4)),Span::new("わたくし".into(),Position::new(2,31).with_columns(6,6,6)),Delimiter::new("《".into(),"》".into())),
+                Token::new_plaintext(Span::new("は".into(),Position::new(2,46).with_columns(11,11,11))),
             ],
             ))]
     fn context_token_works(terms: Vec<term::Term>, input: &str) -> TokenText {
-        let iter = TextIterator::new(
-            ParseContext::new(Arc::new(
-                terms
-                    .into_iter()
-                    .map(|term| (term.body().clone(), term))
-                    .collect(),
-            )),
+        test_helper::new_test_text(&test_helper::new_test_context(terms), input)
+    }
+
+    fn unquoted_terms() -> Vec<term::Term> {
+        vec![
+            term::Term::new(Id::new("tokyo"), "東京".into(), "".into(), "".into(), false),
+            term::Term::new(
+                Id::new("hozumi"),
+                "穂積".into(),
+                "".into(),
+                "".into(),
+                false,
+            ),
+            term::Term::new(
+                Id::new("hozumi_shou"),
+                "穂積しょう".into(),
+                "".into(),
+                "".into(),
+                false,
+            ),
+        ]
+    }
+
+    #[test_case("穂積しょうと東京へ" => TokenText::new(
+            vec![
+                Token::new_term(Span::new("穂積しょう".into(),Position::new(1,0).with_columns(1,1,1)),Id::new("hozumi_shou"),Delimiter::default()),
+                Token::new_plaintext(Span::new("と".into(),Position::new(1,15).with_columns(6,6,6))),
+                Token::new_term(Span::new("東京".into(),Position::new(1,18).with_columns(7,7,7)),Id::new("tokyo"),Delimiter::default()),
+                Token::new_plaintext(Span::new("へ".into(),Position::new(1,24).with_columns(9,9,9))),
+            ],
+            );"longest_match")]
+    #[test_case("東京都と南東京" => TokenText::new(
+            vec![
+                Token::new_plaintext(Span::new("東京都と南東京".into(),Position::new(1,0).with_columns(1,1,1))),
+            ],
+            );"kanji_compound")]
+    #[test_case("東京《とうきょう》" => TokenText::new(
+            vec![
+                Token::new_kanji_ruby(Span::new("東京".into(),Position::new(1,0).with_columns(1,1,1)),Span::new("とうきょう".into(),Position::new(1,9).with_columns(4,4,4)),Delimiter::new("《".into(),"》".into())),
+            ],
+            );"ruby_body")]
+    #[test_case("|東京$東京$" => TokenText::new(
+            vec![
+                Token::new_annotation("|".into(),TokenText::new(vec![Token::new_plaintext(Span::new("東京".into(),Position::new(1,1).with_columns(2,2,2)))]),TokenText::new(vec![
+                    Token::new_plaintext(Span::new("東京".into(),Position::new(1,8).with_columns(5,5,5))),
+                ]),Delimiter::new("$".into(),"$".into())),
+            ],
+            );"annotation")]
+    #[test_case("\"穂積\"しょう" => TokenText::new(
+            vec![
+                Token::new_term(Span::new("穂積".into(),Position::new(1,1).with_columns(2,2,2)),Id::new("hozumi"),Delimiter::new("\"".into(),"\"".into())),
+                Token::new_plaintext(Span::new("しょう".into(),Position::new(1,8).with_columns(5,5,5))),
+            ],
+            );"quoted")]
+    fn unquoted_term_works(input: &str) -> TokenText {
+        test_helper::new_test_text(
+            &test_helper::new_test_context(unquoted_terms())
+                .with_options(ParserOptions::default().with_unquoted_term(true)),
+            input,
+        )
+    }
+
+    #[derive(Debug)]
+    struct Failing;
+
+    impl dialect::Dialect for Failing {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn token<'a>(&self, context: &ParseContext, input: ParsedSpan<'a>) -> IResult<'a> {
+            if input.fragment().starts_with('■') {
+                Err(new_error(input, nom::error::ErrorKind::Verify))
+            } else {
+                dialect::NovelArchives.token(context, input)
+            }
+        }
+    }
+
+    #[test_case("■" => TokenText::new(
+            vec![
+                Token::new_plaintext(Span::new("■".into(),Position::new(1,0).with_columns(1,1,1))),
+            ],
+            );"only")]
+    #[test_case("あ■|漢字(かんじ)■" => TokenText::new(
+            vec![
+                Token::new_plaintext(Span::new("あ■".into(),Position::new(1,0).with_columns(1,1,1))),
+                Token::new_ruby("|".into(),Span::new("漢字".into(),Position::new(1,7).with_columns(4,4,4)),Span::new("かんじ".into(),Position::new(1,14).with_columns(7,7,7)),Delimiter::new("(".into(),")".into())),
+                Token::new_plaintext(Span::new("■".into(),Position::new(1,24).with_columns(11,11,11))),
+            ],
+            );"surrounded")]
+    fn failing_token_works(input: &str) -> TokenText {
+        TextIterator::new(
+            ParseContext::default().with_dialect(Arc::new(Failing)),
             token::ParsedSpan::new(input),
-        );
-        iter.collect()
+        )
+        .collect()
     }
 }
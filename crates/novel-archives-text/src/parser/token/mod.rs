@@ -1,8 +1,11 @@
 use super::*;
 use std::sync::Arc;
+pub mod aozora;
 pub mod complete;
 mod context;
+pub mod dialect;
 pub mod iterator;
+mod options;
 mod span;
 
 #[derive(Debug, PartialEq, Clone, new)]
@@ -10,27 +13,48 @@ pub enum ParsedToken<'a> {
     Term {
         body: ParsedSpan<'a>,
         term_id: Id<term::Term>,
+        delimiter: ParsedDelimiter<'a>,
     },
     Ruby {
+        directive: ParsedSpan<'a>,
         body: ParsedSpan<'a>,
         ruby: ParsedSpan<'a>,
+        delimiter: ParsedDelimiter<'a>,
     },
     KanjiRuby {
         body: ParsedSpan<'a>,
         ruby: ParsedSpan<'a>,
+        delimiter: ParsedDelimiter<'a>,
     },
-    Annotation {
+    MonoRuby {
         body: ParsedSpan<'a>,
+        ruby: ParsedSpan<'a>,
+        pairs: Vec<ParsedRubyPair<'a>>,
+        delimiter: ParsedDelimiter<'a>,
+    },
+    Annotation {
+        directive: ParsedSpan<'a>,
+        body: iterator::TextIterator<'a>,
         description: iterator::TextIterator<'a>,
+        delimiter: ParsedDelimiter<'a>,
     },
     Space(ParsedSpan<'a>),
-    EmphasisMark(ParsedSpan<'a>),
+    EmphasisMark {
+        body: Vec<ParsedToken<'a>>,
+        style: EmphasisStyle,
+        delimiter: ParsedDelimiter<'a>,
+    },
+    Escape {
+        directive: ParsedSpan<'a>,
+        body: ParsedSpan<'a>,
+    },
     Ignore(ParsedSpan<'a>),
     Plaintext(ParsedSpan<'a>),
     NewLine(ParsedSpan<'a>),
 }
 
 pub use context::*;
+pub use options::*;
 pub use span::*;
 
 use nom_extend::character;
@@ -42,22 +66,86 @@ fn without_variation_selector_count(input: &str) -> usize {
         .count()
 }
 
-impl<'a> From<ParsedToken<'a>> for crate::Token {
+impl<'a> From<ParsedToken<'a>> for crate::Token<'a> {
     fn from(token: ParsedToken<'a>) -> Self {
+        PositionCursor::default().token(token)
+    }
+}
+
+impl PositionCursor {
+    pub fn text<'a>(&mut self, tokens: impl IntoIterator<Item = ParsedToken<'a>>) -> TokenText<'a> {
+        TokenText::new(tokens.into_iter().map(|token| self.token(token)).collect())
+    }
+
+    pub fn token<'a>(&mut self, token: ParsedToken<'a>) -> Token<'a> {
         match token {
-            ParsedToken::Term { body, term_id } => Token::new_term(body.into(), term_id),
-            ParsedToken::Ruby { body, ruby } => Token::new_ruby(body.into(), ruby.into()),
-            ParsedToken::KanjiRuby { body, ruby } => {
-                Token::new_kanji_ruby(body.into(), ruby.into())
+            ParsedToken::Term {
+                body,
+                term_id,
+                delimiter,
+            } => Token::new_term(self.span(body), term_id, delimiter.into()),
+            ParsedToken::Ruby {
+                directive,
+                body,
+                ruby,
+                delimiter,
+            } => Token::new_ruby(
+                (*directive.fragment()).into(),
+                self.span(body),
+                self.span(ruby),
+                delimiter.into(),
+            ),
+            ParsedToken::KanjiRuby {
+                body,
+                ruby,
+                delimiter,
+            } => Token::new_kanji_ruby(self.span(body), self.span(ruby), delimiter.into()),
+            ParsedToken::MonoRuby {
+                body,
+                ruby,
+                pairs,
+                delimiter,
+            } => {
+                let body = self.span(body);
+                let pair_bodies: Vec<_> =
+                    pairs.iter().map(|pair| self.span(*pair.body())).collect();
+                let ruby = self.span(ruby);
+                let pair_rubies: Vec<_> =
+                    pairs.iter().map(|pair| self.span(*pair.ruby())).collect();
+                Token::new_mono_ruby(
+                    body,
+                    ruby,
+                    pair_bodies
+                        .into_iter()
+                        .zip(pair_rubies)
+                        .map(|(body, ruby)| RubyPair::new(body, ruby))
+                        .collect(),
+                    delimiter.into(),
+                )
             }
-            ParsedToken::EmphasisMark(body) => Token::new_emphasis_mark(body.into()),
-            ParsedToken::Space(body) => Token::new_spase(body.into()),
-            ParsedToken::Annotation { body, description } => {
-                Token::new_annotation(body.into(), description.collect())
+            ParsedToken::EmphasisMark {
+                body,
+                style,
+                delimiter,
+            } => Token::new_emphasis_mark(self.text(body), style, delimiter.into()),
+            ParsedToken::Space(body) => Token::new_spase(self.span(body)),
+            ParsedToken::Annotation {
+                directive,
+                body,
+                description,
+                delimiter,
+            } => Token::new_annotation(
+                (*directive.fragment()).into(),
+                self.text(body),
+                self.text(description),
+                delimiter.into(),
+            ),
+            ParsedToken::Escape { directive, body } => {
+                Token::new_escape((*directive.fragment()).into(), self.span(body))
             }
-            ParsedToken::Ignore(body) => Token::new_ignore(body.into()),
-            ParsedToken::Plaintext(body) => Token::new_plaintext(body.into()),
-            ParsedToken::NewLine(body) => Token::NewLine(body.into()),
+            ParsedToken::Ignore(body) => Token::new_ignore(self.span(body)),
+            ParsedToken::Plaintext(body) => Token::new_plaintext(self.span(body)),
+            ParsedToken::NewLine(body) => Token::NewLine(self.span(body)),
         }
     }
 }
@@ -68,4 +156,17 @@ pub mod test_helper {
     pub fn new_test_result_span(offset: usize, line: u32, fragment: &str) -> ParsedSpan {
         unsafe { ParsedSpan::new_from_raw_offset(offset, line, fragment, ()) }
     }
+
+    pub fn new_test_context(terms: Vec<term::Term>) -> ParseContext {
+        ParseContext::new(Arc::new(
+            terms
+                .into_iter()
+                .map(|term| (term.body().clone(), term))
+                .collect(),
+        ))
+    }
+
+    pub fn new_test_text<'a>(context: &ParseContext, input: &'a str) -> TokenText<'a> {
+        iterator::TextIterator::new(context.clone(), ParsedSpan::new(input)).collect()
+    }
 }
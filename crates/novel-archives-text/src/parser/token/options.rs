@@ -0,0 +1,211 @@
+use super::*;
+
+const MAX_RUBY_COUNT_PER_BODY_CHAR: usize = 10;
+const MAX_RUBY_COUNT_BODY: usize = 10;
+
+#[derive(Debug, PartialEq, Clone, Copy, new, Getters)]
+pub struct BracketPair {
+    start: char,
+    end: char,
+}
+
+#[derive(Debug, PartialEq, Clone, Getters)]
+pub struct ParserOptions {
+    max_ruby_count_per_body_char: usize,
+    max_ruby_count_body: usize,
+    directives: Vec<char>,
+    ruby_brackets: Vec<BracketPair>,
+    emphasis_mark_brackets: Vec<BracketPair>,
+    term_brackets: Vec<BracketPair>,
+    annotation_brackets: Vec<BracketPair>,
+    kanji_ruby: bool,
+    mono_ruby: bool,
+    split_group_ruby: bool,
+    directive_ruby: bool,
+    emphasis_mark: bool,
+    annotation: bool,
+    term: bool,
+    unquoted_term: bool,
+    escape: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_ruby_count_per_body_char: MAX_RUBY_COUNT_PER_BODY_CHAR,
+            max_ruby_count_body: MAX_RUBY_COUNT_BODY,
+            directives: vec!['|', '｜'],
+            ruby_brackets: vec![
+                BracketPair::new('《', '》'),
+                BracketPair::new('⟪', '⟫'),
+                BracketPair::new('(', ')'),
+                BracketPair::new('（', '）'),
+            ],
+            emphasis_mark_brackets: vec![BracketPair::new('《', '》'), BracketPair::new('⟪', '⟫')],
+            term_brackets: vec![BracketPair::new('"', '"'), BracketPair::new('”', '”')],
+            annotation_brackets: vec![BracketPair::new('$', '$'), BracketPair::new('＄', '＄')],
+            kanji_ruby: true,
+            mono_ruby: true,
+            split_group_ruby: false,
+            directive_ruby: true,
+            emphasis_mark: true,
+            annotation: true,
+            term: true,
+            unquoted_term: false,
+            escape: true,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn with_max_ruby_count_per_body_char(self, max_ruby_count_per_body_char: usize) -> Self {
+        Self {
+            max_ruby_count_per_body_char,
+            ..self
+        }
+    }
+
+    pub fn with_max_ruby_count_body(self, max_ruby_count_body: usize) -> Self {
+        Self {
+            max_ruby_count_body,
+            ..self
+        }
+    }
+
+    pub fn with_directives(self, directives: Vec<char>) -> Self {
+        Self { directives, ..self }
+    }
+
+    pub fn with_ruby_brackets(self, ruby_brackets: Vec<BracketPair>) -> Self {
+        Self {
+            ruby_brackets,
+            ..self
+        }
+    }
+
+    pub fn with_emphasis_mark_brackets(self, emphasis_mark_brackets: Vec<BracketPair>) -> Self {
+        Self {
+            emphasis_mark_brackets,
+            ..self
+        }
+    }
+
+    pub fn with_term_brackets(self, term_brackets: Vec<BracketPair>) -> Self {
+        Self {
+            term_brackets,
+            ..self
+        }
+    }
+
+    pub fn with_annotation_brackets(self, annotation_brackets: Vec<BracketPair>) -> Self {
+        Self {
+            annotation_brackets,
+            ..self
+        }
+    }
+
+    pub fn with_kanji_ruby(self, kanji_ruby: bool) -> Self {
+        Self { kanji_ruby, ..self }
+    }
+
+    pub fn with_mono_ruby(self, mono_ruby: bool) -> Self {
+        Self { mono_ruby, ..self }
+    }
+
+    pub fn with_split_group_ruby(self, split_group_ruby: bool) -> Self {
+        Self {
+            split_group_ruby,
+            ..self
+        }
+    }
+
+    pub fn with_directive_ruby(self, directive_ruby: bool) -> Self {
+        Self {
+            directive_ruby,
+            ..self
+        }
+    }
+
+    pub fn with_emphasis_mark(self, emphasis_mark: bool) -> Self {
+        Self {
+            emphasis_mark,
+            ..self
+        }
+    }
+
+    pub fn with_annotation(self, annotation: bool) -> Self {
+        Self { annotation, ..self }
+    }
+
+    pub fn with_term(self, term: bool) -> Self {
+        Self { term, ..self }
+    }
+
+    pub fn with_unquoted_term(self, unquoted_term: bool) -> Self {
+        Self {
+            unquoted_term,
+            ..self
+        }
+    }
+
+    pub fn with_escape(self, escape: bool) -> Self {
+        Self { escape, ..self }
+    }
+
+    pub fn is_escapable(&self, c: char) -> bool {
+        self.is_start_directive(c)
+            || (self.term && (self.is_start_term(c) || self.is_end_term(c)))
+            || (self.annotation && (self.is_start_annotation(c) || self.is_end_annotation(c)))
+            || ((self.kanji_ruby || self.mono_ruby || self.directive_ruby)
+                && (self.is_start_ruby(c) || self.is_end_ruby(c)))
+            || (self.emphasis_mark
+                && (self.is_start_emphasis_mark(c) || self.is_end_emphasis_mark(c)))
+    }
+
+    pub fn is_plaintext(&self, c: char) -> bool {
+        !(self.is_start_directive(c)
+            || self.is_start_term(c)
+            || self.is_start_emphasis_mark(c)
+            || character::is_any_space(c)
+            || character::is_any_newline(c)
+            || character::is_kanji(c))
+    }
+
+    pub fn is_start_directive(&self, c: char) -> bool {
+        self.directives.contains(&c)
+    }
+
+    pub fn is_start_ruby(&self, c: char) -> bool {
+        self.ruby_brackets.iter().any(|pair| pair.start == c)
+    }
+
+    pub fn is_end_ruby(&self, c: char) -> bool {
+        self.ruby_brackets.iter().any(|pair| pair.end == c)
+    }
+
+    pub fn is_start_emphasis_mark(&self, c: char) -> bool {
+        self.emphasis_mark_brackets
+            .iter()
+            .any(|pair| pair.start == c)
+    }
+
+    pub fn is_end_emphasis_mark(&self, c: char) -> bool {
+        self.emphasis_mark_brackets.iter().any(|pair| pair.end == c)
+    }
+
+    pub fn is_start_term(&self, c: char) -> bool {
+        self.term_brackets.iter().any(|pair| pair.start == c)
+    }
+
+    pub fn is_end_term(&self, c: char) -> bool {
+        self.term_brackets.iter().any(|pair| pair.end == c)
+    }
+
+    pub fn is_start_annotation(&self, c: char) -> bool {
+        self.annotation_brackets.iter().any(|pair| pair.start == c)
+    }
+
+    pub fn is_end_annotation(&self, c: char) -> bool {
+        self.annotation_brackets.iter().any(|pair| pair.end == c)
+    }
+}
@@ -2,11 +2,147 @@ use crate::Position;
 
 pub type ParsedSpan<'a> = nom_locate::LocatedSpan<&'a str>;
 
-impl<'a> From<ParsedSpan<'a>> for crate::token::Span {
+impl<'a> From<ParsedSpan<'a>> for crate::token::Span<'a> {
     fn from(span: ParsedSpan<'a>) -> Self {
-        crate::Span::new(
-            span.fragment().to_string(),
-            Position::new(span.location_line() as usize, span.location_offset()),
+        PositionCursor::default().span(span)
+    }
+}
+
+impl<'a> From<ParsedSpan<'a>> for Position {
+    fn from(span: ParsedSpan<'a>) -> Self {
+        PositionCursor::default().position(&span)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PositionCursor {
+    line: usize,
+    line_start: usize,
+    line_text: String,
+    position: Position,
+}
+
+impl PositionCursor {
+    pub(crate) fn position(&mut self, span: &ParsedSpan) -> Position {
+        let line = span.location_line() as usize;
+        let byte_offset = span.location_offset();
+        if line != self.line
+            || byte_offset < *self.position.byte_offset()
+            || byte_offset > self.line_start + self.line_text.len()
+        {
+            self.line = line;
+            self.line_start = byte_offset - (span.get_column() - 1);
+            self.line_text = String::from_utf8_lossy(span.get_line_beginning()).into_owned();
+            self.position = Position::new(line, self.line_start).with_columns(1, 1, 1);
+        }
+        let skipped = self
+            .line_text
+            .get(self.position.byte_offset() - self.line_start..byte_offset - self.line_start)
+            .unwrap_or_default();
+        self.position = crate::Span::new(skipped.into(), self.position.clone()).end_position();
+        self.position.clone()
+    }
+
+    pub(crate) fn span<'a>(&mut self, span: ParsedSpan<'a>) -> crate::Span<'a> {
+        crate::Span::new((*span.fragment()).into(), self.position(&span))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct ParsedDelimiter<'a> {
+    start: ParsedSpan<'a>,
+    end: ParsedSpan<'a>,
+}
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct ParsedRubyPair<'a> {
+    body: ParsedSpan<'a>,
+    ruby: ParsedSpan<'a>,
+}
+
+impl<'a> From<ParsedDelimiter<'a>> for crate::token::Delimiter<'a> {
+    fn from(delimiter: ParsedDelimiter<'a>) -> Self {
+        crate::Delimiter::new(
+            (*delimiter.start.fragment()).into(),
+            (*delimiter.end.fragment()).into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::token::iterator::TextIterator;
+    use crate::parser::token::ParseContext;
+    use crate::{Token, TokenText};
+    use nom::InputTake;
+    use test_case::test_case;
+
+    fn columns(position: &Position) -> (usize, usize, usize, usize, usize) {
+        (
+            *position.line(),
+            *position.byte_offset(),
+            *position.column(),
+            *position.grapheme_column(),
+            *position.utf16_column(),
         )
     }
+
+    #[test_case("あいう", 0=>(1, 0, 1, 1, 1);"start")]
+    #[test_case("あ\ne\u{301}👨\u{200d}👩\u{200d}👧は", 25=>(2, 25, 8, 3, 11);"combining_and_zwj")]
+    #[test_case("𠮷と", 4=>(1, 4, 2, 2, 3);"surrogate_pair")]
+    fn position_from_works(input: &str, offset: usize) -> (usize, usize, usize, usize, usize) {
+        let (span, _) = ParsedSpan::new(input).take_split(offset);
+        columns(&span.into())
+    }
+
+    #[test_case(vec![0, 5, 9, 14]=>vec![(1, 0, 1, 1, 1), (2, 5, 1, 1, 1), (2, 9, 2, 2, 3), (3, 14, 1, 1, 1)];"forward")]
+    #[test_case(vec![9, 5, 0]=>vec![(2, 9, 2, 2, 3), (2, 5, 1, 1, 1), (1, 0, 1, 1, 1)];"backward")]
+    fn position_cursor_works(offsets: Vec<usize>) -> Vec<(usize, usize, usize, usize, usize)> {
+        let input = ParsedSpan::new("𠮷\n𠮷𠮷\n𠮷");
+        let mut cursor = PositionCursor::default();
+        offsets
+            .into_iter()
+            .map(|offset| columns(&cursor.position(&input.take_split(offset).0)))
+            .collect()
+    }
+
+    #[test]
+    fn position_reused_buffer_works() {
+        let mut buffer = String::with_capacity(16);
+        buffer.push_str("𠮷𠮷あ");
+        let (span, _) = ParsedSpan::new(&buffer).take_split(8);
+        assert_eq!(columns(&span.into()), (1, 8, 3, 3, 5));
+        buffer.clear();
+        buffer.push_str("ああああ");
+        let (span, _) = ParsedSpan::new(&buffer).take_split(6);
+        assert_eq!(columns(&span.into()), (1, 6, 3, 3, 3));
+    }
+
+    #[test]
+    fn position_chained_inputs_works() {
+        let first = "𠮷𠮷|あ(い)";
+        let second = "ああ|あ(い)";
+        let text: TokenText = TextIterator::new(ParseContext::default(), ParsedSpan::new(first))
+            .chain(TextIterator::new(
+                ParseContext::default(),
+                ParsedSpan::new(second),
+            ))
+            .collect();
+        let positions: Vec<_> = text
+            .iter()
+            .filter_map(|token| match token {
+                Token::Ruby { body, .. } => Some(columns(body.original_position())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(positions, vec![(1, 9, 4, 4, 6), (1, 7, 4, 4, 4)]);
+    }
+
+    #[test_case("あ\ne\u{301}👨\u{200d}👩\u{200d}👧は", 4=>(2, 28, 9, 4, 12);"same_line")]
+    #[test_case("あ\nい", 0=>(2, 7, 2, 2, 2);"multi_line")]
+    fn span_end_position_works(input: &str, offset: usize) -> (usize, usize, usize, usize, usize) {
+        let (span, _) = ParsedSpan::new(input).take_split(offset);
+        columns(&crate::Span::from(span).end_position())
+    }
 }
@@ -0,0 +1,283 @@
+use crate::parser::nom_extend::character;
+use crate::{EmphasisStyle, Token, TokenText};
+use std::fmt::Write;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SerializeMode {
+    Lossless,
+    Canonical,
+}
+
+const CANONICAL_DIRECTIVE: &str = "|";
+const CANONICAL_TERM_START: &str = "\"";
+const CANONICAL_TERM_END: &str = "\"";
+const CANONICAL_RUBY_START: &str = "《";
+const CANONICAL_RUBY_END: &str = "》";
+const CANONICAL_MONO_RUBY_SEPARATOR: &str = "・";
+const CANONICAL_ANNOTATION_START: &str = "$";
+const CANONICAL_ANNOTATION_END: &str = "$";
+const CANONICAL_EMPHASIS_MARK_START: &str = "《《";
+const CANONICAL_EMPHASIS_MARK_END: &str = "》》";
+
+pub fn serialize(text: &TokenText, mode: SerializeMode) -> String {
+    let mut s = String::new();
+    write_text(&mut s, text, mode);
+    s
+}
+
+pub fn serialize_token(token: &Token, mode: SerializeMode) -> String {
+    let mut s = String::new();
+    write_token(&mut s, token, mode);
+    s
+}
+
+fn write_text(s: &mut String, text: &TokenText, mode: SerializeMode) {
+    for token in text.iter() {
+        write_token(s, token, mode);
+    }
+}
+
+fn write_token(s: &mut String, token: &Token, mode: SerializeMode) {
+    let lossless = mode == SerializeMode::Lossless;
+    match token {
+        Token::Term {
+            body, delimiter, ..
+        } => {
+            let (start, end) = if lossless {
+                (delimiter.start().as_ref(), delimiter.end().as_ref())
+            } else {
+                (CANONICAL_TERM_START, CANONICAL_TERM_END)
+            };
+            write!(s, "{}{}{}", start, body.body(), end).unwrap();
+        }
+        Token::Ruby {
+            directive,
+            body,
+            ruby,
+            delimiter,
+        } => {
+            let (directive, start, end) = if lossless {
+                (
+                    directive.as_ref(),
+                    delimiter.start().as_ref(),
+                    delimiter.end().as_ref(),
+                )
+            } else {
+                (
+                    CANONICAL_DIRECTIVE,
+                    CANONICAL_RUBY_START,
+                    CANONICAL_RUBY_END,
+                )
+            };
+            write!(
+                s,
+                "{}{}{}{}{}",
+                directive,
+                body.body(),
+                start,
+                ruby.body(),
+                end
+            )
+            .unwrap();
+        }
+        Token::KanjiRuby {
+            body,
+            ruby,
+            delimiter,
+        } => {
+            let (start, end) = if lossless {
+                (delimiter.start().as_ref(), delimiter.end().as_ref())
+            } else {
+                (CANONICAL_RUBY_START, CANONICAL_RUBY_END)
+            };
+            write!(s, "{}{}{}{}", body.body(), start, ruby.body(), end).unwrap();
+        }
+        Token::MonoRuby {
+            body,
+            ruby,
+            pairs,
+            delimiter,
+        } => {
+            if lossless {
+                write!(
+                    s,
+                    "{}{}{}{}",
+                    body.body(),
+                    delimiter.start(),
+                    ruby.body(),
+                    delimiter.end()
+                )
+                .unwrap();
+            } else {
+                let ruby = pairs
+                    .iter()
+                    .map(|pair| pair.ruby().body().as_ref())
+                    .collect::<Vec<_>>()
+                    .join(CANONICAL_MONO_RUBY_SEPARATOR);
+                write!(
+                    s,
+                    "{}{}{}{}",
+                    body.body(),
+                    CANONICAL_RUBY_START,
+                    ruby,
+                    CANONICAL_RUBY_END
+                )
+                .unwrap();
+            }
+        }
+        Token::Annotation {
+            directive,
+            body,
+            description,
+            delimiter,
+        } => {
+            let (directive, start, end) = if lossless {
+                (
+                    directive.as_ref(),
+                    delimiter.start().as_ref(),
+                    delimiter.end().as_ref(),
+                )
+            } else {
+                (
+                    CANONICAL_DIRECTIVE,
+                    CANONICAL_ANNOTATION_START,
+                    CANONICAL_ANNOTATION_END,
+                )
+            };
+            s.push_str(directive);
+            write_text(s, body, mode);
+            s.push_str(start);
+            write_text(s, description, mode);
+            s.push_str(end);
+        }
+        Token::EmphasisMark {
+            body,
+            style,
+            delimiter,
+        } => {
+            if lossless {
+                s.push_str(delimiter.start());
+            } else if *style == EmphasisStyle::default() {
+                s.push_str(CANONICAL_EMPHASIS_MARK_START);
+            } else {
+                write!(
+                    s,
+                    "{}{}{}",
+                    CANONICAL_EMPHASIS_MARK_START,
+                    style.name(),
+                    CANONICAL_DIRECTIVE
+                )
+                .unwrap();
+            }
+            write_text(s, body, mode);
+            s.push_str(if lossless {
+                delimiter.end()
+            } else {
+                CANONICAL_EMPHASIS_MARK_END
+            });
+        }
+        Token::Escape { directive, body } => {
+            let directive = if lossless {
+                directive.as_ref()
+            } else {
+                CANONICAL_DIRECTIVE
+            };
+            write!(s, "{}{}", directive, body.body()).unwrap();
+        }
+        Token::Plaintext(body) if !lossless => write_escaped(s, body.body()),
+        Token::Spase(body) | Token::Ignore(body) | Token::Plaintext(body) => {
+            s.push_str(body.body())
+        }
+        Token::NewLine(body) => {
+            if lossless {
+                s.push_str(body.body())
+            } else {
+                s.push('\n')
+            }
+        }
+    }
+}
+
+// Escapes the chars which would otherwise be parsed as markup.
+fn write_escaped(s: &mut String, input: &str) {
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let needs_escape = character::is_start_directive(c)
+            || character::is_start_term(c)
+            || character::is_start_annotation(c)
+            || (character::is_start_emphasis_mark(c)
+                && chars
+                    .peek()
+                    .is_some_and(|&next| character::is_start_emphasis_mark(next)))
+            || (character::is_start_ruby(c) && s.chars().last().is_some_and(character::is_kanji));
+        if needs_escape {
+            s.push_str(CANONICAL_DIRECTIVE);
+        }
+        s.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::token::test_helper::{new_test_context, new_test_text};
+    use test_case::test_case;
+
+    fn parse(input: &str) -> TokenText {
+        let terms = vec![crate::term::Term::new(
+            crate::Id::new("term_id1"),
+            "穂積".into(),
+            "ほづみ".into(),
+            "".into(),
+            false,
+        )];
+        new_test_text(&new_test_context(terms), input)
+    }
+
+    #[test_case("｜ほげ（ふが)と漢字（かんじ）"=>"｜ほげ（ふが)と漢字（かんじ）";"ruby")]
+    #[test_case("漢字(かん じ)"=>"漢字(かん じ)";"mono_ruby")]
+    #[test_case("⟪《傍点》⟫です"=>"⟪《傍点》⟫です";"emphasis_mark")]
+    #[test_case("《《白ゴマ｜傍点》》"=>"《《白ゴマ｜傍点》》";"emphasis_mark_style")]
+    #[test_case("｜漢字＄せつめい$"=>"｜漢字＄せつめい$";"annotation")]
+    #[test_case("|漢字（かんじ）$せつめい$"=>"|漢字（かんじ）$せつめい$";"annotation_with_ruby")]
+    #[test_case("”穂積\"\r\n|(かっこ)"=>"”穂積\"\r\n|(かっこ)";"term_and_ignore")]
+    #[test_case("漢字｜(かっこ)と|\"穂積\"と|《《傍点》》"=>"漢字｜(かっこ)と|\"穂積\"と|《《傍点》》";"escape")]
+    #[test_case("|US｜＄$ドル$と《《a|》》》"=>"|US｜＄$ドル$と《《a|》》》";"escape_in_markup")]
+    fn serialize_lossless_works(input: &str) -> String {
+        serialize(&parse(input), SerializeMode::Lossless)
+    }
+
+    #[test_case("｜ほげ（ふが)と漢字（かんじ）"=>"|ほげ《ふが》と漢字《かんじ》";"ruby")]
+    #[test_case("漢字(かん じ)"=>"漢字《かん・じ》";"mono_ruby")]
+    #[test_case("⟪《傍点》⟫です"=>"《《傍点》》です";"emphasis_mark")]
+    #[test_case("《《白ゴマ｜傍点》》"=>"《《白ゴマ|傍点》》";"emphasis_mark_style")]
+    #[test_case("｜漢字＄せつめい$"=>"|漢字$せつめい$";"annotation")]
+    #[test_case("|漢字（かんじ）$せつめい$"=>"|漢字《かんじ》$せつめい$";"annotation_with_ruby")]
+    #[test_case("”穂積\"\r\n|(かっこ)"=>"\"穂積\"\n|(かっこ)";"term_and_ignore")]
+    #[test_case("漢字｜(かっこ)と|\"穂積\"と|《《傍点》》"=>"漢字|(かっこ)と|\"穂積|\"と|《《傍点》》";"escape")]
+    #[test_case("\"有\""=>"|\"有|\"";"unknown_term")]
+    fn serialize_canonical_works(input: &str) -> String {
+        serialize(&parse(input), SerializeMode::Canonical)
+    }
+
+    #[test_case(vec!["漢字", "(かっこ)"];"kanji_ruby")]
+    #[test_case(vec!["\"穂積\"と|", "$"];"term_and_directive")]
+    #[test_case(vec!["《《傍点》》"];"emphasis_mark")]
+    fn serialize_canonical_escape_works(plaintexts: Vec<&str>) {
+        let text = TokenText::new(
+            plaintexts
+                .into_iter()
+                .map(|body| {
+                    Token::new_plaintext(crate::Span::new(body.into(), crate::Position::default()))
+                })
+                .collect(),
+        );
+        let canonical = serialize(&text, SerializeMode::Canonical);
+        let reparsed = parse(&canonical);
+        assert_eq!(serialize(&reparsed, SerializeMode::Canonical), canonical);
+        assert_eq!(
+            crate::aozora::export(&reparsed),
+            crate::aozora::export(&text)
+        );
+    }
+}
@@ -1,4 +1,10 @@
 use super::*;
+use parser::nom_extend::character;
+use parser::token::{iterator::TextIterator, ParseContext, ParsedSpan};
+
+const HALF_WIDTH_KATAKANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+const VOICEABLE_KATAKANA: &str = "カキクケコサシスセソタチツテトハヒフヘホ";
+const SEMI_VOICEABLE_KATAKANA: &str = "ハヒフヘホ";
 
 #[derive(Debug, PartialEq, Clone, new, Getters)]
 pub struct Term {
@@ -7,4 +13,165 @@ pub struct Term {
     ruby: String,
     description: String,
     has_detail: bool,
+    #[new(default)]
+    aliases: Vec<String>,
+}
+
+impl Term {
+    pub fn with_aliases(self, aliases: Vec<String>) -> Self {
+        Self { aliases, ..self }
+    }
+
+    pub fn surface_forms(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.body).chain(self.aliases.iter())
+    }
+
+    pub fn parse_description(&self, context: &ParseContext) -> TokenText<'_> {
+        TextIterator::new(context.clone(), ParsedSpan::new(&self.description)).collect()
+    }
+
+    pub fn references(&self, context: &ParseContext) -> Vec<Id<Term>> {
+        let description = self.parse_description(context);
+        let mut references: Vec<Id<Term>> = vec![];
+        for (term_id, _) in quoted_references(&description) {
+            if !references.contains(term_id) {
+                references.push(term_id.clone());
+            }
+        }
+        references
+    }
+}
+
+pub fn quoted_references<'t, 'a>(text: &'t TokenText<'a>) -> Vec<(&'t Id<Term>, &'t Span<'a>)> {
+    let mut references = vec![];
+    write_quoted_references(&mut references, text);
+    references
+}
+
+fn write_quoted_references<'t, 'a>(
+    references: &mut Vec<(&'t Id<Term>, &'t Span<'a>)>,
+    text: &'t TokenText<'a>,
+) {
+    for token in text.iter() {
+        match token {
+            Token::Term {
+                body,
+                term_id,
+                delimiter,
+            } if !delimiter.start().is_empty() => references.push((term_id, body)),
+            Token::Annotation {
+                body, description, ..
+            } => {
+                write_quoted_references(references, body);
+                write_quoted_references(references, description);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn normalize(input: &str) -> String {
+    normalized_chars(input).map(|(_, c)| c).collect()
+}
+
+// Yields each normalized char with the byte offset where its source chars end.
+pub fn normalized_chars(input: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut chars = input.char_indices().peekable();
+    std::iter::from_fn(move || loop {
+        let (offset, raw) = chars.next()?;
+        if is_variation_selector(raw) {
+            continue;
+        }
+        let mut c = fold_width(raw);
+        let mut end = offset + raw.len_utf8();
+        while let Some(&(next_offset, next)) = chars.peek() {
+            let voiced = match next {
+                '\u{ff9e}' if VOICEABLE_KATAKANA.contains(c) => std::char::from_u32(c as u32 + 1),
+                '\u{ff9e}' if c == 'ウ' => Some('ヴ'),
+                '\u{ff9f}' if SEMI_VOICEABLE_KATAKANA.contains(c) => {
+                    std::char::from_u32(c as u32 + 2)
+                }
+                next if is_variation_selector(next) => Some(c),
+                _ => None,
+            };
+            match voiced {
+                Some(voiced) => {
+                    c = voiced;
+                    end = next_offset + next.len_utf8();
+                    chars.next();
+                }
+                None => break,
+            }
+        }
+        return Some((end, c));
+    })
+}
+
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{ff01}'..='\u{ff5e}' => std::char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        '\u{ff61}'..='\u{ff9d}' => HALF_WIDTH_KATAKANA
+            .chars()
+            .nth(c as usize - 0xff61)
+            .unwrap_or(c),
+        '\u{ff9e}' => '゛',
+        '\u{ff9f}' => '゜',
+        _ => c,
+    }
+}
+
+#[allow(clippy::manual_range_contains)]
+fn is_variation_selector(c: char) -> bool {
+    character::is_kanji_variation_selector(c) || (c >= '\u{fe00}' && c <= '\u{fe0f}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::token::test_helper::new_test_context;
+    use test_case::test_case;
+
+    #[test_case("穂積"=>"穂積";"plain")]
+    #[test_case("葛\u{e0100}城"=>"葛城";"variation_selector")]
+    #[test_case("ＡＢＣ１２３　"=>"ABC123 ";"full_width")]
+    #[test_case("ｼｮｳ"=>"ショウ";"half_width_katakana")]
+    #[test_case("ｶﾞﾊﾟｳﾞ"=>"ガパヴ";"half_width_voiced")]
+    fn normalize_works(input: &str) -> String {
+        normalize(input)
+    }
+
+    #[test_case("\"葛城\"と|注$\"王都\"$と\"葛城\""=>vec!["katsuragi", "capital"];"quoted")]
+    #[test_case("葛城と\"未知\""=>Vec::<String>::new();"unquoted_and_unknown")]
+    fn references_works(description: &str) -> Vec<String> {
+        let terms = vec![
+            Term::new(
+                Id::new("katsuragi"),
+                "葛城".into(),
+                "かつらぎ".into(),
+                "".into(),
+                false,
+            ),
+            Term::new(
+                Id::new("capital"),
+                "王都".into(),
+                "おうと".into(),
+                "".into(),
+                false,
+            ),
+        ];
+        let context = new_test_context(terms)
+            .with_options(parser::token::ParserOptions::default().with_unquoted_term(true));
+        Term::new(
+            Id::new("hozumi"),
+            "穂積".into(),
+            "ほづみ".into(),
+            description.into(),
+            false,
+        )
+        .references(&context)
+        .into_iter()
+        .map(|term_id| term_id.value().clone())
+        .collect()
+    }
 }
@@ -1,67 +1,301 @@
+use crate::serializer;
 use crate::term::Term;
 use crate::Id;
-use std::{fmt::Write, ops::Deref};
+use std::borrow::Cow;
+use std::ops::Deref;
+use unicode_segmentation::UnicodeSegmentation;
 #[derive(Debug, PartialEq, Clone, new)]
-pub struct TokenText(Vec<Token>);
+pub struct TokenText<'a>(Vec<Token<'a>>);
 
-impl ToString for TokenText {
+impl<'a> TokenText<'a> {
+    pub fn into_owned(self) -> TokenText<'static> {
+        TokenText(self.0.into_iter().map(Token::into_owned).collect())
+    }
+}
+
+impl<'a> ToString for TokenText<'a> {
     fn to_string(&self) -> std::string::String {
-        let mut s = String::new();
-        for t in self.0.iter() {
-            write!(&mut s, "{}", t.to_string()).unwrap();
-        }
-        s
+        serializer::serialize(self, serializer::SerializeMode::Lossless)
     }
 }
 
-impl Deref for TokenText {
-    type Target = Vec<Token>;
+impl<'a> Deref for TokenText<'a> {
+    type Target = Vec<Token<'a>>;
     fn deref(&self) -> &<Self as std::ops::Deref>::Target {
         &self.0
     }
 }
 
 #[derive(Debug, PartialEq, Clone, new)]
-pub enum Token {
-    Term { body: Span, term_id: Id<Term> },
-    Ruby { body: Span, ruby: Span },
-    KanjiRuby { body: Span, ruby: Span },
-    Annotation { body: Span, description: TokenText },
-    EmphasisMark(Span),
-    Spase(Span),
-    Ignore(Span),
-    Plaintext(Span),
-    NewLine(Span),
-}
-
-impl ToString for Token {
-    fn to_string(&self) -> std::string::String {
+pub enum Token<'a> {
+    Term {
+        body: Span<'a>,
+        term_id: Id<Term>,
+        delimiter: Delimiter<'a>,
+    },
+    Ruby {
+        directive: Cow<'a, str>,
+        body: Span<'a>,
+        ruby: Span<'a>,
+        delimiter: Delimiter<'a>,
+    },
+    KanjiRuby {
+        body: Span<'a>,
+        ruby: Span<'a>,
+        delimiter: Delimiter<'a>,
+    },
+    MonoRuby {
+        body: Span<'a>,
+        ruby: Span<'a>,
+        pairs: Vec<RubyPair<'a>>,
+        delimiter: Delimiter<'a>,
+    },
+    Annotation {
+        directive: Cow<'a, str>,
+        body: TokenText<'a>,
+        description: TokenText<'a>,
+        delimiter: Delimiter<'a>,
+    },
+    EmphasisMark {
+        body: TokenText<'a>,
+        style: EmphasisStyle,
+        delimiter: Delimiter<'a>,
+    },
+    Escape {
+        directive: Cow<'a, str>,
+        body: Span<'a>,
+    },
+    Spase(Span<'a>),
+    Ignore(Span<'a>),
+    Plaintext(Span<'a>),
+    NewLine(Span<'a>),
+}
+
+impl<'a> Token<'a> {
+    pub fn into_owned(self) -> Token<'static> {
         match self {
-            Token::Term { body, .. } => format!("\"{}\"", body.body()),
-            Token::Ruby { body, ruby } => format!("|{}《{}》", body.body(), ruby.body()),
-            Token::EmphasisMark(body) => body.body().clone(),
-            Token::Spase(body) => body.body().clone(),
-            Token::KanjiRuby { body, ruby } => format!("{}({})", body.body(), ruby.body()),
-            Token::Annotation { body, description } => {
-                format!("|{}${}$", body.body(), description.to_string())
-            }
-            Token::Ignore(body) => body.body().clone(),
-            Token::Plaintext(body) => body.body().clone(),
-            Token::NewLine(body) => body.body().to_string(),
+            Token::Term {
+                body,
+                term_id,
+                delimiter,
+            } => Token::Term {
+                body: body.into_owned(),
+                term_id,
+                delimiter: delimiter.into_owned(),
+            },
+            Token::Ruby {
+                directive,
+                body,
+                ruby,
+                delimiter,
+            } => Token::Ruby {
+                directive: Cow::Owned(directive.into_owned()),
+                body: body.into_owned(),
+                ruby: ruby.into_owned(),
+                delimiter: delimiter.into_owned(),
+            },
+            Token::KanjiRuby {
+                body,
+                ruby,
+                delimiter,
+            } => Token::KanjiRuby {
+                body: body.into_owned(),
+                ruby: ruby.into_owned(),
+                delimiter: delimiter.into_owned(),
+            },
+            Token::MonoRuby {
+                body,
+                ruby,
+                pairs,
+                delimiter,
+            } => Token::MonoRuby {
+                body: body.into_owned(),
+                ruby: ruby.into_owned(),
+                pairs: pairs.into_iter().map(RubyPair::into_owned).collect(),
+                delimiter: delimiter.into_owned(),
+            },
+            Token::Annotation {
+                directive,
+                body,
+                description,
+                delimiter,
+            } => Token::Annotation {
+                directive: Cow::Owned(directive.into_owned()),
+                body: body.into_owned(),
+                description: description.into_owned(),
+                delimiter: delimiter.into_owned(),
+            },
+            Token::EmphasisMark {
+                body,
+                style,
+                delimiter,
+            } => Token::EmphasisMark {
+                body: body.into_owned(),
+                style,
+                delimiter: delimiter.into_owned(),
+            },
+            Token::Escape { directive, body } => Token::Escape {
+                directive: Cow::Owned(directive.into_owned()),
+                body: body.into_owned(),
+            },
+            Token::Spase(body) => Token::Spase(body.into_owned()),
+            Token::Ignore(body) => Token::Ignore(body.into_owned()),
+            Token::Plaintext(body) => Token::Plaintext(body.into_owned()),
+            Token::NewLine(body) => Token::NewLine(body.into_owned()),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, new, Getters)]
-pub struct Span {
-    body: String,
-    originel_position: Position,
+impl<'a> ToString for Token<'a> {
+    fn to_string(&self) -> std::string::String {
+        serializer::serialize_token(self, serializer::SerializeMode::Lossless)
+    }
+}
+
+// Names used to select a style, followed by the Aozora Bunko annotation for it.
+const EMPHASIS_STYLES: [(EmphasisStyle, &str, &str); 8] = [
+    (EmphasisStyle::Sesame, "ゴマ", "傍点"),
+    (EmphasisStyle::OpenSesame, "白ゴマ", "白ゴマ傍点"),
+    (EmphasisStyle::Dot, "丸", "丸傍点"),
+    (EmphasisStyle::Circle, "白丸", "白丸傍点"),
+    (EmphasisStyle::DoubleCircle, "二重丸", "二重丸傍点"),
+    (EmphasisStyle::Triangle, "黒三角", "黒三角傍点"),
+    (EmphasisStyle::OpenTriangle, "白三角", "白三角傍点"),
+    (EmphasisStyle::SideLine, "傍線", "傍線"),
+];
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum EmphasisStyle {
+    #[default]
+    Sesame,
+    OpenSesame,
+    Dot,
+    Circle,
+    DoubleCircle,
+    Triangle,
+    OpenTriangle,
+    SideLine,
+}
+
+impl EmphasisStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        EMPHASIS_STYLES
+            .iter()
+            .find(|(_, style_name, _)| *style_name == name)
+            .map(|(style, _, _)| *style)
+    }
+
+    pub fn from_aozora_name(name: &str) -> Option<Self> {
+        EMPHASIS_STYLES
+            .iter()
+            .find(|(_, _, aozora_name)| *aozora_name == name)
+            .map(|(style, _, _)| *style)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.names().0
+    }
+
+    pub fn aozora_name(&self) -> &'static str {
+        self.names().1
+    }
+
+    fn names(&self) -> (&'static str, &'static str) {
+        EMPHASIS_STYLES
+            .iter()
+            .find(|(style, _, _)| style == self)
+            .map(|(_, name, aozora_name)| (*name, *aozora_name))
+            .unwrap()
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Default, new, Getters)]
+pub struct Delimiter<'a> {
+    start: Cow<'a, str>,
+    end: Cow<'a, str>,
+}
+
+impl<'a> Delimiter<'a> {
+    pub fn into_owned(self) -> Delimiter<'static> {
+        Delimiter::new(
+            Cow::Owned(self.start.into_owned()),
+            Cow::Owned(self.end.into_owned()),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct RubyPair<'a> {
+    body: Span<'a>,
+    ruby: Span<'a>,
+}
+
+impl<'a> RubyPair<'a> {
+    pub fn into_owned(self) -> RubyPair<'static> {
+        RubyPair::new(self.body.into_owned(), self.ruby.into_owned())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct Span<'a> {
+    body: Cow<'a, str>,
+    original_position: Position,
+}
+
+impl<'a> Span<'a> {
+    pub fn into_owned(self) -> Span<'static> {
+        Span::new(Cow::Owned(self.body.into_owned()), self.original_position)
+    }
+
+    pub fn end_position(&self) -> Position {
+        let start = &self.original_position;
+        let (column, grapheme_column, utf16_column) = match self.body.rfind('\n') {
+            Some(index) => {
+                let last_line = &self.body[index + 1..];
+                (
+                    last_line.chars().count() + 1,
+                    last_line.graphemes(true).count() + 1,
+                    last_line.encode_utf16().count() + 1,
+                )
+            }
+            None => (
+                start.column + self.body.chars().count(),
+                start.grapheme_column + self.body.graphemes(true).count(),
+                start.utf16_column + self.body.encode_utf16().count(),
+            ),
+        };
+        Position {
+            line: start.line + self.body.matches('\n').count(),
+            byte_offset: start.byte_offset + self.body.len(),
+            column,
+            grapheme_column,
+            utf16_column,
+        }
+    }
+}
+
+// Columns are 1-based like `line`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default, new, Getters)]
 pub struct Position {
     line: usize,
     byte_offset: usize,
+    #[new(default)]
+    column: usize,
+    #[new(default)]
+    grapheme_column: usize,
+    #[new(default)]
+    utf16_column: usize,
+}
+
+impl Position {
+    pub fn with_columns(self, column: usize, grapheme_column: usize, utf16_column: usize) -> Self {
+        Self {
+            column,
+            grapheme_column,
+            utf16_column,
+            ..self
+        }
+    }
 }
 
 #[cfg(test)]
@@ -69,12 +303,57 @@ mod test {
     use super::*;
     use test_case::test_case;
 
-    #[test_case(Token::new_kanji_ruby(Span::new("漢字".into(),Position::default()),Span::new("かんじ".into(),Position::default()))=>"漢字(かんじ)")]
+    #[test_case(Token::new_kanji_ruby(Span::new("漢字".into(),Position::default()),Span::new("かんじ".into(),Position::default()),Delimiter::new("(".into(),")".into()))=>"漢字(かんじ)")]
+    #[test_case(Token::new_kanji_ruby(Span::new("漢字".into(),Position::default()),Span::new("かんじ".into(),Position::default()),Delimiter::new("（".into(),"》".into()))=>"漢字（かんじ》";"kanji_ruby_mixed")]
+    #[test_case(Token::new_ruby("｜".into(),Span::new("ほげ".into(),Position::default()),Span::new("ふが".into(),Position::default()),Delimiter::new("（".into(),"）".into()))=>"｜ほげ（ふが）";"ruby_wide")]
+    #[test_case(Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("傍点".into(),Position::default()))]),EmphasisStyle::Sesame,Delimiter::new("《《".into(),"》》".into()))=>"《《傍点》》")]
+    #[test_case(Token::new_emphasis_mark(TokenText::new(vec![Token::new_plaintext(Span::new("傍点".into(),Position::default()))]),EmphasisStyle::OpenSesame,Delimiter::new("《《白ゴマ｜".into(),"》》".into()))=>"《《白ゴマ｜傍点》》";"emphasis_mark_style")]
+    #[test_case(Token::new_annotation("|".into(),TokenText::new(vec![Token::new_plaintext(Span::new("漢字".into(),Position::default()))]),TokenText::new(vec![Token::new_plaintext(Span::new("せつめい".into(),Position::default()))]),Delimiter::new("＄".into(),"＄".into()))=>"|漢字＄せつめい＄";"annotation_wide")]
     #[test_case(Token::new_spase(Span::new("  ".into(),Position::default()))=>"  ")]
     #[test_case(Token::new_ignore(Span::new("|".into(),Position::default()))=>"|")]
     #[test_case(Token::new_new_line(Span::new("\n".into(),Position::default()))=>"\n")]
-    #[test_case(Token::new_term(Span::new("ほげ".into(),Position::default()),Id::new("term_id1"))=>"\"ほげ\"")]
+    #[test_case(Token::new_term(Span::new("ほげ".into(),Position::default()),Id::new("term_id1"),Delimiter::new("\"".into(),"”".into()))=>"\"ほげ”")]
     fn token_to_string_works(token: Token) -> String {
         token.to_string()
     }
+
+    fn parse(input: &str) -> TokenText {
+        crate::parser::token::iterator::TextIterator::new(
+            crate::parser::token::ParseContext::default(),
+            crate::parser::token::ParsedSpan::new(input),
+        )
+        .collect()
+    }
+
+    #[test_case("|漢字(かんじ)と《《傍点》》"=>true;"ruby_and_emphasis_mark")]
+    #[test_case("|注$漢字(かんじ)$"=>true;"annotation")]
+    fn parse_borrows_input(input: &str) -> bool {
+        parse(input).iter().all(|token| match token {
+            Token::Ruby {
+                directive,
+                body,
+                ruby,
+                delimiter,
+            } => [directive, body.body(), ruby.body(), delimiter.start()]
+                .iter()
+                .all(|body| matches!(body, Cow::Borrowed(_))),
+            Token::Annotation { description, .. } => description.iter().all(|token| {
+                matches!(token, Token::KanjiRuby { body, .. } if matches!(body.body(), Cow::Borrowed(_)))
+            }),
+            Token::EmphasisMark { body, .. } => body.iter().all(|token| {
+                matches!(token, Token::Plaintext(body) if matches!(body.body(), Cow::Borrowed(_)))
+            }),
+            Token::Plaintext(body) => matches!(body.body(), Cow::Borrowed(_)),
+            _ => false,
+        })
+    }
+
+    #[test_case("|漢字(かんじ)と\n漢字(かん・じ)$注$")]
+    fn into_owned_works(input: &str) {
+        let owned = {
+            let input = input.to_string();
+            parse(&input).into_owned()
+        };
+        assert_eq!(owned, parse(input));
+    }
 }
@@ -0,0 +1,261 @@
+use super::*;
+use parser::nom_extend::character;
+use parser::token::ParseContext;
+use parser::{Diagnostic, DiagnosticKind};
+use std::collections::BTreeMap;
+use term::Term;
+
+const MIN_RUBY_COUNT_PER_BODY_CHAR: f64 = 0.5;
+const MAX_RUBY_COUNT_PER_BODY_CHAR: usize = 5;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Script {
+    Hiragana,
+    Katakana,
+    Latin,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+type References = BTreeMap<String, Vec<(Id<Term>, Span<'static>)>>;
+
+// Spans are relative to the description of the term each diagnostic belongs to.
+#[derive(Debug, PartialEq, Clone, new, Getters)]
+pub struct TermDiagnostic {
+    term_id: Id<Term>,
+    diagnostic: Diagnostic,
+}
+
+pub fn validate_ruby(text: &TokenText) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    write_ruby_diagnostics(&mut diagnostics, text);
+    diagnostics
+}
+
+fn write_ruby_diagnostics(diagnostics: &mut Vec<Diagnostic>, text: &TokenText) {
+    for token in text.iter() {
+        match token {
+            Token::Ruby { body, ruby, .. } | Token::KanjiRuby { body, ruby, .. } => {
+                diagnostics.extend(check(body, ruby))
+            }
+            Token::MonoRuby { pairs, .. } => {
+                for pair in pairs {
+                    diagnostics.extend(check(pair.body(), pair.ruby()));
+                }
+            }
+            Token::Annotation {
+                body, description, ..
+            } => {
+                write_ruby_diagnostics(diagnostics, body);
+                write_ruby_diagnostics(diagnostics, description);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn validate_terms(context: &ParseContext) -> Vec<TermDiagnostic> {
+    let mut diagnostics = vec![];
+    let mut references = References::new();
+    for term in context.terms() {
+        let (description, description_diagnostics) =
+            parser::parse_with_diagnostics(context.clone(), term.description());
+        diagnostics.extend(
+            description_diagnostics
+                .into_iter()
+                .map(|diagnostic| TermDiagnostic::new(term.id().clone(), diagnostic)),
+        );
+        references.insert(
+            term.id().value().clone(),
+            term::quoted_references(&description)
+                .into_iter()
+                .map(|(term_id, span)| (term_id.clone(), span.clone().into_owned()))
+                .collect(),
+        );
+    }
+    let mut visits = BTreeMap::new();
+    for term in context.terms() {
+        write_cycle_diagnostics(&mut diagnostics, &references, &mut visits, term.id());
+    }
+    diagnostics
+}
+
+// Reports each reference which leads back to a term whose references are still being followed.
+fn write_cycle_diagnostics(
+    diagnostics: &mut Vec<TermDiagnostic>,
+    references: &References,
+    visits: &mut BTreeMap<String, Visit>,
+    term_id: &Id<Term>,
+) {
+    if visits.contains_key(term_id.value()) {
+        return;
+    }
+    visits.insert(term_id.value().clone(), Visit::InProgress);
+    for (reference, span) in references.get(term_id.value()).into_iter().flatten() {
+        match visits.get(reference.value()) {
+            Some(Visit::InProgress) => diagnostics.push(TermDiagnostic::new(
+                term_id.clone(),
+                Diagnostic::from_span(DiagnosticKind::CyclicTermReference, span.clone()),
+            )),
+            Some(Visit::Done) => {}
+            None => write_cycle_diagnostics(diagnostics, references, visits, reference),
+        }
+    }
+    visits.insert(term_id.value().clone(), Visit::Done);
+}
+
+fn check(body: &Span, ruby: &Span) -> Option<Diagnostic> {
+    let reading = ruby.body();
+    let kind = if reading.chars().any(character::is_kanji) {
+        DiagnosticKind::RubyContainsKanji
+    } else if is_mixed_scripts(reading) {
+        DiagnosticKind::RubyMixedScripts
+    } else if is_implausible_length(body.body(), reading) {
+        DiagnosticKind::RubyImplausibleLength
+    } else {
+        return None;
+    };
+    Some(Diagnostic::from_span(kind, ruby.clone()))
+}
+
+fn script(c: char) -> Option<Script> {
+    match c {
+        // The prolonged sound mark and middle dot are shared by both kana.
+        'ー' | '・' => None,
+        c if character::is_hiragana(c) => Some(Script::Hiragana),
+        c if character::is_katakana(c) => Some(Script::Katakana),
+        c if c.is_ascii_alphabetic() || character::is_wide_alphabetic(c) => Some(Script::Latin),
+        _ => None,
+    }
+}
+
+fn is_mixed_scripts(reading: &str) -> bool {
+    let mut scripts = reading.chars().filter_map(script);
+    scripts
+        .next()
+        .is_some_and(|first| scripts.any(|script| script != first))
+}
+
+// Only kana readings are expected to follow the body length.
+fn is_implausible_length(body: &str, reading: &str) -> bool {
+    if !body.chars().all(character::is_kanji_related)
+        || !reading
+            .chars()
+            .all(|c| character::is_hiragana(c) || character::is_katakana(c))
+    {
+        return false;
+    }
+    let body_count = body
+        .chars()
+        .filter(|&c| !character::is_kanji_variation_selector(c))
+        .count();
+    let reading_count = reading.chars().count();
+    (reading_count as f64) < body_count as f64 * MIN_RUBY_COUNT_PER_BODY_CHAR
+        || reading_count > body_count * MAX_RUBY_COUNT_PER_BODY_CHAR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::token;
+    use parser::token::test_helper::new_test_context;
+    use test_case::test_case;
+
+    fn diagnostic(
+        kind: DiagnosticKind,
+        body: &str,
+        offset: usize,
+    ) -> (DiagnosticKind, String, usize) {
+        (kind, body.into(), offset)
+    }
+
+    #[test_case("漢字(かんじ)と|ほげ(ふが)"=>Vec::<(DiagnosticKind, String, usize)>::new();"valid")]
+    #[test_case("魔法(マジック)と|アイ(ai)と東京(とーきょー)"=>Vec::<(DiagnosticKind, String, usize)>::new();"valid_gloss")]
+    #[test_case("|漢字(感じ)"=>vec![
+        diagnostic(DiagnosticKind::RubyContainsKanji, "感じ", 8),
+    ];"kanji")]
+    #[test_case("漢字(かんジ)と漢字(kanじ)"=>vec![
+        diagnostic(DiagnosticKind::RubyMixedScripts, "かんジ", 7),
+        diagnostic(DiagnosticKind::RubyMixedScripts, "kanじ", 27),
+    ];"mixed_scripts")]
+    #[test_case("東京都庁(と)と漢(かんかんかんか)"=>vec![
+        diagnostic(DiagnosticKind::RubyImplausibleLength, "と", 13),
+        diagnostic(DiagnosticKind::RubyImplausibleLength, "かんかんかんか", 24),
+    ];"implausible_length")]
+    #[test_case("漢字(かん・字)"=>vec![
+        diagnostic(DiagnosticKind::RubyContainsKanji, "字", 16),
+    ];"mono_ruby")]
+    #[test_case("|注$漢字(かん字)$"=>vec![
+        diagnostic(DiagnosticKind::RubyContainsKanji, "かん字", 12),
+    ];"in_annotation")]
+    fn validate_ruby_works(input: &str) -> Vec<(DiagnosticKind, String, usize)> {
+        let text: TokenText = token::iterator::TextIterator::new(
+            token::ParseContext::default(),
+            token::ParsedSpan::new(input),
+        )
+        .collect();
+        validate_ruby(&text)
+            .into_iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.severity(), &parser::Severity::Warning);
+                (
+                    *diagnostic.kind(),
+                    diagnostic.span().body().to_string(),
+                    *diagnostic.span().original_position().byte_offset(),
+                )
+            })
+            .collect()
+    }
+
+    fn term_context(terms: &[(&str, &str, &str)]) -> token::ParseContext {
+        new_test_context(
+            terms
+                .iter()
+                .map(|(id, body, description)| {
+                    Term::new(
+                        Id::new(*id),
+                        body.to_string(),
+                        "".into(),
+                        description.to_string(),
+                        false,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test_case(&[("a", "穂積", "\"葛城\"の友人。"), ("b", "葛城", "")]=>Vec::<(String, DiagnosticKind, String, usize)>::new();"valid")]
+    #[test_case(&[("a", "穂積", "\"葛城\"の友人。")]=>vec![
+        ("a".into(), DiagnosticKind::UnknownTerm, "葛城".into(), 1),
+    ];"unknown")]
+    #[test_case(&[("a", "穂積", "\"葛城\"を見よ。"), ("b", "葛城", "\"穂積\"を見よ。")]=>vec![
+        ("b".into(), DiagnosticKind::CyclicTermReference, "穂積".into(), 1),
+    ];"cycle")]
+    #[test_case(&[("a", "穂積", "\"葛城\"と\"王都\"。"), ("b", "葛城", "\"王都\"。"), ("c", "王都", "\"穂積\"。")]=>vec![
+        ("b".into(), DiagnosticKind::CyclicTermReference, "王都".into(), 1),
+        ("a".into(), DiagnosticKind::CyclicTermReference, "王都".into(), 12),
+    ];"chain")]
+    #[test_case(&[("a", "穂積", "|注$\"穂積\"$")]=>vec![
+        ("a".into(), DiagnosticKind::CyclicTermReference, "穂積".into(), 6),
+    ];"self")]
+    fn validate_terms_works(
+        terms: &[(&str, &str, &str)],
+    ) -> Vec<(String, DiagnosticKind, String, usize)> {
+        validate_terms(&term_context(terms))
+            .into_iter()
+            .map(|term_diagnostic| {
+                let diagnostic = term_diagnostic.diagnostic();
+                (
+                    term_diagnostic.term_id().value().clone(),
+                    *diagnostic.kind(),
+                    diagnostic.span().body().to_string(),
+                    *diagnostic.span().original_position().byte_offset(),
+                )
+            })
+            .collect()
+    }
+}